@@ -0,0 +1,210 @@
+use wasm_bindgen::prelude::*;
+
+use super::{Board, Difficulty, MoveError, Player, State, opponent, starting_player, winning_lines};
+
+/// Ultimate tic-tac-toe: a 3x3 grid of classic boards. The cell a move is
+/// played in picks the sub-board the opponent must play in next, unless that
+/// sub-board is already decided, in which case any open sub-board will do.
+/// Winning three sub-boards in a row wins the game.
+#[wasm_bindgen]
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BigBoard {
+    boards: Vec<Board>,
+    moves: Vec<usize>,
+    active_board: Option<usize>,
+    status: State,
+    turn: Player,
+    winner: Player
+}
+
+#[wasm_bindgen]
+impl BigBoard {
+
+    #[wasm_bindgen(constructor)]
+    pub fn new(start_player: Player) -> Self {
+        let start_player = starting_player(start_player);
+        BigBoard {
+            boards: (0..9).map(|_| Board::new(start_player, Difficulty::EASY)).collect(),
+            moves: vec![],
+            active_board: None,
+            status: State::INPROGRESS,
+            turn: start_player,
+            winner: Player::EMPTY
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn make_move(&mut self, sub_board: usize, cell: usize) -> Result<(), MoveError> {
+        if self.status != State::INPROGRESS {
+            return Err(MoveError::GameOver);
+        }
+        if sub_board >= 9 || cell >= 9 {
+            return Err(MoveError::OutOfRange);
+        }
+        if self.active_board.is_some_and(|active| active != sub_board)
+            || self.boards[sub_board].is_game_over() {
+            return Err(MoveError::WrongSubBoard);
+        }
+
+        // Turns alternate across the whole game rather than within one
+        // sub-board, so the sub-board is told whose move it is.
+        let board = &mut self.boards[sub_board];
+        board.turn = self.turn;
+        board.make_move(cell)?;
+
+        self.moves.push(sub_board * 9 + cell);
+        self.turn = opponent(self.turn);
+        self.active_board = if self.boards[cell].is_game_over() { None } else { Some(cell) };
+        self.change_board_state();
+        Ok(())
+    }
+
+    fn change_board_state(&mut self) {
+        let owners = self.sub_board_winners();
+        let meta_winner = winning_lines(3, 3).into_iter().find_map(|line| {
+            let owner = owners[line[0]];
+            if owner != Player::EMPTY && line.iter().all(|&index| owners[index] == owner) {
+                Some(owner)
+            } else {
+                None
+            }
+        });
+
+        if let Some(owner) = meta_winner {
+            self.status = State::RESULTED;
+            self.winner = owner;
+            self.active_board = None;
+        } else if self.boards.iter().all(|board| board.is_game_over()) {
+            self.status = State::DRAW;
+            self.active_board = None;
+        }
+    }
+
+    fn sub_board_winners(&self) -> Vec<Player> {
+        self.boards.iter().map(|board| board.get_winner()).collect()
+    }
+
+    /// Winner of each sub-board as Player discriminants, EMPTY while it is
+    /// undecided or drawn.
+    #[wasm_bindgen]
+    pub fn get_sub_board_winners(&self) -> Vec<i32> {
+        self.sub_board_winners().into_iter().map(|player| player as i32).collect()
+    }
+
+    /// The sub-board the next move must be played in, or None when the player
+    /// to move may pick any open sub-board.
+    #[wasm_bindgen]
+    pub fn get_active_board(&self) -> Option<usize> {
+        self.active_board
+    }
+
+    /// All 81 cells as Player discriminants, sub-board by sub-board, so cell
+    /// `cell` of sub-board `sub_board` is at `sub_board * 9 + cell`.
+    #[wasm_bindgen]
+    pub fn get_matrix(&self) -> Vec<i32> {
+        self.boards.iter().flat_map(|board| board.get_matrix_codes()).collect()
+    }
+
+    /// Legal moves as `sub_board * 9 + cell` indices.
+    #[wasm_bindgen]
+    pub fn get_available_moves(&self) -> Vec<usize> {
+        if self.status != State::INPROGRESS {
+            return vec![];
+        }
+
+        let mut available_moves: Vec<usize> = vec![];
+        for (index, board) in self.boards.iter().enumerate() {
+            if self.active_board.is_none_or(|active| active == index)
+                && !board.is_game_over() {
+                for cell in board.get_available_moves() {
+                    available_moves.push(index * 9 + cell);
+                }
+            }
+        }
+        available_moves
+    }
+
+    #[wasm_bindgen]
+    pub fn get_move_history(&self) -> Vec<usize> {
+        self.moves.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_board_state(&self) -> State {
+        self.status
+    }
+
+    #[wasm_bindgen]
+    pub fn get_winner(&self) -> Player {
+        self.winner
+    }
+
+    #[wasm_bindgen]
+    pub fn get_current_turn(&self) -> Player {
+        self.turn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // X takes the middle row of sub-boards 0 and 2 and the bottom row of
+    // sub-board 1, with O always sending X back where it needs to be.
+    const X_WINS_TOP_ROW: [(usize, usize); 17] = [
+        (0, 3), (3, 0), (0, 4), (4, 0), (0, 5), (5, 1), (1, 6), (6, 1), (1, 7),
+        (7, 1), (1, 8), (8, 2), (2, 3), (3, 2), (2, 4), (4, 2), (2, 5)
+    ];
+
+    #[test]
+    fn move_picks_the_next_sub_board() {
+        let mut board = BigBoard::new(Player::X);
+        assert_eq!(board.get_active_board(), None);
+        assert_eq!(board.get_available_moves().len(), 81);
+
+        board.make_move(0, 4).unwrap();
+        assert_eq!(board.get_active_board(), Some(4));
+        assert_eq!(board.get_available_moves(), (36..45).collect::<Vec<usize>>());
+
+        assert_eq!(board.make_move(0, 0), Err(MoveError::WrongSubBoard));
+        assert_eq!(board.make_move(4, 9), Err(MoveError::OutOfRange));
+        board.make_move(4, 0).unwrap();
+        assert_eq!(board.make_move(0, 4), Err(MoveError::AlreadyFilled));
+        assert_eq!(board.get_matrix()[4], Player::X as i32);
+        assert_eq!(board.get_matrix()[36], Player::O as i32);
+        assert_eq!(board.get_current_turn(), Player::X);
+    }
+
+    #[test]
+    fn decided_sub_board_frees_the_next_move() {
+        let mut board = BigBoard::new(Player::X);
+        for &(sub_board, cell) in &X_WINS_TOP_ROW[..5] {
+            board.make_move(sub_board, cell).unwrap();
+        }
+        assert_eq!(board.get_sub_board_winners()[0], Player::X as i32);
+
+        // Sending X to the decided sub-board 0 lets X play anywhere else.
+        board.make_move(5, 0).unwrap();
+        assert_eq!(board.get_active_board(), None);
+        assert_eq!(board.make_move(0, 0), Err(MoveError::WrongSubBoard));
+        assert!(!board.get_available_moves().iter().any(|&index| index < 9));
+        board.make_move(8, 8).unwrap();
+    }
+
+    #[test]
+    fn three_sub_boards_in_a_row_win() {
+        let mut board = BigBoard::new(Player::X);
+        for &(sub_board, cell) in &X_WINS_TOP_ROW {
+            assert_eq!(board.get_board_state(), State::INPROGRESS);
+            board.make_move(sub_board, cell).unwrap();
+        }
+
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(&board.get_sub_board_winners()[..3], &[Player::X as i32; 3]);
+        assert_eq!(board.get_active_board(), None);
+        assert!(board.get_available_moves().is_empty());
+        assert_eq!(board.get_move_history().len(), X_WINS_TOP_ROW.len());
+        assert_eq!(board.make_move(5, 5), Err(MoveError::GameOver));
+    }
+}
@@ -0,0 +1,347 @@
+use wasm_bindgen::prelude::*;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use super::{Difficulty, Ignored, MoveError, Player, State, entropy_rng, opponent, starting_player};
+
+const SIZE: usize = 4;
+const CELLS: usize = SIZE * SIZE * SIZE;
+const WIN_SCORE: i32 = 100_000;
+
+/// 3D tic-tac-toe on a 4x4x4 cube: four in a row along any of the 76 lines
+/// through the cube wins. Cell `layer * 16 + row * 4 + col` is
+/// (layer, row, col), so moves are indices 0..63.
+#[wasm_bindgen]
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Board3D {
+    matrix: Vec<Player>,
+    moves: Vec<usize>,
+    status: State,
+    turn: Player,
+    winner: Player,
+    difficulty: Difficulty,
+    lines: Vec<[usize; SIZE]>,
+    rng: Ignored<StdRng>
+}
+
+#[wasm_bindgen]
+impl Board3D {
+
+    #[wasm_bindgen(constructor)]
+    pub fn new(start_player: Player, difficulty: Difficulty) -> Self {
+        let start_player = starting_player(start_player);
+        Board3D {
+            matrix: vec![Player::EMPTY; CELLS],
+            moves: vec![],
+            status: State::INPROGRESS,
+            turn: start_player,
+            winner: Player::EMPTY,
+            difficulty,
+            lines: cube_lines(),
+            rng: entropy_rng()
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn make_move(&mut self, move_position: usize) -> Result<(), MoveError> {
+        if self.status != State::INPROGRESS {
+            return Err(MoveError::GameOver);
+        }
+        if move_position >= CELLS {
+            return Err(MoveError::OutOfRange);
+        }
+        if self.matrix[move_position] != Player::EMPTY {
+            return Err(MoveError::AlreadyFilled);
+        }
+
+        self.apply_move(move_position);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn undo_move(&mut self) -> Result<usize, JsValue> {
+        self.revert_move().ok_or_else(|| JsValue::from("No Moves To Undo."))
+    }
+
+    fn apply_move(&mut self, move_position: usize) {
+        self.matrix[move_position] = self.turn;
+        self.moves.push(move_position);
+        if self.completes_line(move_position) {
+            self.status = State::RESULTED;
+            self.winner = self.turn;
+        } else if self.moves.len() >= CELLS {
+            self.status = State::DRAW;
+        }
+        self.turn = opponent(self.turn);
+    }
+
+    fn revert_move(&mut self) -> Option<usize> {
+        let move_position = self.moves.pop()?;
+        self.matrix[move_position] = Player::EMPTY;
+        self.turn = opponent(self.turn);
+        self.status = State::INPROGRESS;
+        self.winner = Player::EMPTY;
+        Some(move_position)
+    }
+
+    // Only lines through the last move can have just been completed.
+    fn completes_line(&self, move_position: usize) -> bool {
+        let player = self.matrix[move_position];
+        self.lines.iter().any(|line| line.contains(&move_position)
+            && line.iter().all(|&cell| self.matrix[cell] == player))
+    }
+
+    #[wasm_bindgen]
+    pub fn get_board_state(&self) -> State {
+        self.status
+    }
+
+    #[wasm_bindgen]
+    pub fn get_winner(&self) -> Player {
+        self.winner
+    }
+
+    #[wasm_bindgen]
+    pub fn get_current_turn(&self) -> Player {
+        self.turn
+    }
+
+    #[wasm_bindgen]
+    pub fn get_matrix(&self) -> Vec<i32> {
+        self.matrix.iter().map(|&player| player as i32).collect()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_move_history(&self) -> Vec<usize> {
+        self.moves.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_available_moves(&self) -> Vec<usize> {
+        if self.status != State::INPROGRESS {
+            return vec![];
+        }
+        (0..CELLS).filter(|&cell| self.matrix[cell] == Player::EMPTY).collect()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_winning_line(&self) -> Option<Vec<usize>> {
+        if self.status != State::RESULTED {
+            return None;
+        }
+        self.lines.iter()
+            .find(|line| line.iter().all(|&cell| self.matrix[cell] == self.winner))
+            .map(|line| line.to_vec())
+    }
+
+    /// EASY plays at random; the other difficulties take an immediate win or
+    /// block one, then search 1, 2 or 3 plies deep.
+    #[wasm_bindgen]
+    pub fn get_next_move(&mut self) -> Option<usize> {
+        let available_moves = self.get_available_moves();
+        if available_moves.is_empty() {
+            return None;
+        }
+
+        let depth = match self.difficulty {
+            Difficulty::EASY => {
+                let move_index = self.rng.0.gen_range(0, available_moves.len());
+                return Some(available_moves[move_index]);
+            }
+            Difficulty::MEDIUM => 1,
+            Difficulty::DIFFICULT => 2,
+            Difficulty::IMPOSSIBLE => 3,
+        };
+
+        let player = self.turn;
+        if let Some(mv) = self.winning_cell(player) {
+            return Some(mv);
+        }
+        if let Some(mv) = self.winning_cell(opponent(player)) {
+            return Some(mv);
+        }
+        self.best_move(depth)
+    }
+
+    // An empty cell that would complete a line for `player`.
+    fn winning_cell(&self, player: Player) -> Option<usize> {
+        self.lines.iter().find_map(|line| {
+            let mut empty = None;
+            for &cell in line.iter() {
+                if self.matrix[cell] == Player::EMPTY && empty.is_none() {
+                    empty = Some(cell);
+                } else if self.matrix[cell] != player {
+                    return None;
+                }
+            }
+            empty
+        })
+    }
+
+    fn best_move(&mut self, depth: u32) -> Option<usize> {
+        let mut best: Vec<usize> = vec![];
+        let mut best_score = -WIN_SCORE * 2;
+        for mv in self.get_available_moves() {
+            self.apply_move(mv);
+            let score = -self.negamax(depth - 1, -WIN_SCORE * 2, WIN_SCORE * 2);
+            self.revert_move();
+            if score > best_score {
+                best_score = score;
+                best = vec![mv];
+            } else if score == best_score {
+                best.push(mv);
+            }
+        }
+
+        if best.is_empty() {
+            return None;
+        }
+        let move_index = self.rng.0.gen_range(0, best.len());
+        Some(best[move_index])
+    }
+
+    // Depth-limited alpha-beta from the view of the side to move. Quicker
+    // wins score higher, so the remaining depth is added to a win.
+    fn negamax(&mut self, depth: u32, mut alpha: i32, beta: i32) -> i32 {
+        match self.status {
+            State::RESULTED => return -(WIN_SCORE + depth as i32),
+            State::DRAW => return 0,
+            State::INPROGRESS => {}
+        }
+        if depth == 0 {
+            return self.heuristic_score(self.turn);
+        }
+
+        let mut best_score = -WIN_SCORE * 2;
+        for mv in self.get_available_moves() {
+            self.apply_move(mv);
+            let score = -self.negamax(depth - 1, -beta, -alpha);
+            self.revert_move();
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    // Lines still open to only one player, weighted by how full they are.
+    fn heuristic_score(&self, player: Player) -> i32 {
+        const WEIGHTS: [i32; SIZE + 1] = [0, 1, 10, 100, WIN_SCORE];
+        let other = opponent(player);
+        self.lines.iter().map(|line| {
+            let mine = line.iter().filter(|&&cell| self.matrix[cell] == player).count();
+            let theirs = line.iter().filter(|&&cell| self.matrix[cell] == other).count();
+            if theirs == 0 {
+                WEIGHTS[mine]
+            } else if mine == 0 {
+                -WEIGHTS[theirs]
+            } else {
+                0
+            }
+        }).sum()
+    }
+}
+
+// All 76 lines of the cube: every start cell and direction (taking one of each
+// pair of opposite directions) whose four steps stay inside the cube.
+fn cube_lines() -> Vec<[usize; SIZE]> {
+    let mut lines: Vec<[usize; SIZE]> = vec![];
+    let size = SIZE as isize;
+    for d_layer in -1..=1isize {
+        for d_row in -1..=1isize {
+            for d_col in -1..=1isize {
+                if (d_layer, d_row, d_col) <= (0, 0, 0) {
+                    continue;
+                }
+                for start in 0..CELLS as isize {
+                    let (layer, row, col) = (start / 16, start / 4 % 4, start % 4);
+                    let end = |base: isize, step: isize| base + step * (size - 1);
+                    let in_cube = |value: isize| value >= 0 && value < size;
+                    if !in_cube(end(layer, d_layer)) || !in_cube(end(row, d_row))
+                        || !in_cube(end(col, d_col)) {
+                        continue;
+                    }
+                    let mut line = [0; SIZE];
+                    for i in 0..size {
+                        line[i as usize] = ((layer + d_layer * i) * 16 + (row + d_row * i) * 4
+                            + col + d_col * i) as usize;
+                    }
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays `line` for X with O answering on three cells of the top layer
+    // that no line of X's uses.
+    fn x_plays(line: [usize; SIZE]) -> Board3D {
+        let mut board = Board3D::new(Player::X, Difficulty::EASY);
+        let replies = [53, 54, 59];
+        for (index, &cell) in line.iter().enumerate() {
+            assert_eq!(board.get_board_state(), State::INPROGRESS);
+            board.make_move(cell).unwrap();
+            if index < replies.len() {
+                board.make_move(replies[index]).unwrap();
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn cube_has_76_lines() {
+        assert_eq!(cube_lines().len(), 76);
+    }
+
+    #[test]
+    fn straight_line_wins() {
+        let board = x_plays([0, 1, 2, 3]);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(board.get_winning_line(), Some(vec![0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn face_diagonal_wins() {
+        let board = x_plays([0, 5, 10, 15]);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(board.get_winning_line(), Some(vec![0, 5, 10, 15]));
+    }
+
+    #[test]
+    fn space_diagonal_wins() {
+        let board = x_plays([3, 22, 41, 60]);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(board.get_winning_line(), Some(vec![3, 22, 41, 60]));
+    }
+
+    #[test]
+    fn moves_are_checked_against_the_cube() {
+        let mut board = x_plays([0, 1, 2, 3]);
+        assert_eq!(board.make_move(4), Err(MoveError::GameOver));
+        assert_eq!(board.undo_move().unwrap(), 3);
+        assert_eq!(board.make_move(64), Err(MoveError::OutOfRange));
+        assert_eq!(board.make_move(0), Err(MoveError::AlreadyFilled));
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+    }
+
+    #[test]
+    fn ai_takes_a_win_and_blocks_a_loss() {
+        let mut board = x_plays([0, 1, 2, 3]);
+        board.undo_move().unwrap();
+        board.difficulty = Difficulty::MEDIUM;
+        assert_eq!(board.get_next_move(), Some(3));
+
+        // Taking back O's last reply leaves O to move, and 3 is the block.
+        board.undo_move().unwrap();
+        assert_eq!(board.get_current_turn(), Player::O);
+        assert_eq!(board.get_next_move(), Some(3));
+    }
+}
@@ -1,8 +1,23 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use wasm_bindgen::prelude::*;
-use rand::Rng;
+use rand::{Rng, SeedableRng, FromEntropy};
+use rand::rngs::StdRng;
+use serde::{Serialize, Deserialize};
+
+mod big_board;
+mod board3d;
+
+pub use big_board::BigBoard;
+pub use board3d::Board3D;
 
 #[wasm_bindgen]
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum State {
     DRAW,
     RESULTED,
@@ -10,7 +25,7 @@ pub enum State {
 }
 
 #[wasm_bindgen]
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum Player {
     X = 1,
     O = 2,
@@ -18,197 +33,4157 @@ pub enum Player {
 }
 
 #[wasm_bindgen]
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Difficulty {
     EASY = 0,
     MEDIUM = 1,
-    DIFFICULT= 2
+    DIFFICULT= 2,
+    IMPOSSIBLE = 3
+}
+
+/// Under MISERE the player who completes a line loses instead of winning.
+#[wasm_bindgen]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Variant {
+    STANDARD = 0,
+    MISERE = 1
+}
+
+impl Player {
+    pub fn from_u8(value: u8) -> Option<Player> {
+        match value {
+            0 => Some(Player::EMPTY),
+            1 => Some(Player::X),
+            2 => Some(Player::O),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+impl Difficulty {
+    pub fn from_u8(value: u8) -> Option<Difficulty> {
+        match value {
+            0 => Some(Difficulty::EASY),
+            1 => Some(Difficulty::MEDIUM),
+            2 => Some(Difficulty::DIFFICULT),
+            3 => Some(Difficulty::IMPOSSIBLE),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(&self) -> u8 {
+        self.clone() as u8
+    }
+}
+
+const WINNING_MASKS: [u16; 8] = [
+    0b000_000_111, 0b000_111_000, 0b111_000_000,
+    0b001_001_001, 0b010_010_010, 0b100_100_100,
+    0b100_010_001, 0b001_010_100
+];
+
+// wasm-bindgen derives `From<MoveError> for JsValue`, so errors are thrown
+// to JS as the bare discriminant and callers can switch on it.
+#[wasm_bindgen]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum MoveError {
+    OutOfRange = 0,
+    AlreadyFilled = 1,
+    GameOver = 2,
+    WrongSubBoard = 3
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            MoveError::OutOfRange => "Illegal Position Supplied. Try Again.",
+            MoveError::AlreadyFilled => "Position Already Filled. Try Again",
+            MoveError::GameOver => "Game is already over.",
+            MoveError::WrongSubBoard => "Move Must Be In The Active Sub-Board. Try Again.",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// Names a cell of the 3x3 board as column letter then row number: columns
+/// run a-c from left to right and rows 1-3 from top to bottom, so "a1" is
+/// cell 0 and "c3" is cell 8. None for cells off the board.
+#[wasm_bindgen]
+pub fn move_to_notation(pos: usize) -> Option<String> {
+    if pos >= 9 {
+        return None;
+    }
+    let column = (b'a' + (pos % 3) as u8) as char;
+    Some(format!("{}{}", column, pos / 3 + 1))
+}
+
+#[wasm_bindgen]
+pub fn notation_to_move(notation: &str) -> Result<usize, JsValue> {
+    parse_notation(notation, 3)
+        .ok_or_else(|| JsValue::from("Illegal Notation Supplied. Try Again."))
+}
+
+// Reads move_to_notation's notation on a board `size` cells wide, where the
+// columns run from "a" and the rows from 1 as far as the board goes.
+fn parse_notation(notation: &str, size: usize) -> Option<usize> {
+    let notation = notation.trim().to_lowercase();
+    let mut chars = notation.chars();
+    let column = chars.next().filter(char::is_ascii_lowercase)? as usize - 'a' as usize;
+    let row = chars.as_str();
+    if row.is_empty() || row.starts_with('0') || !row.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let row: usize = row.parse().ok()?;
+    if column >= size || row > size {
+        return None;
+    }
+    Some((row - 1) * size + column)
+}
+
+#[wasm_bindgen]
+pub fn move_error_message(error: MoveError) -> String {
+    error.to_string()
+}
+
+// One bit per cell for each player, mirroring the matrix of a classic 3x3
+// board so wins can be checked with a handful of ANDs during search.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+struct Bitboard {
+    x: u16,
+    o: u16,
+}
+
+impl Bitboard {
+    fn set(&mut self, position: usize, player: Player) {
+        match player {
+            Player::X => self.x |= 1 << position,
+            Player::O => self.o |= 1 << position,
+            Player::EMPTY => {
+                self.x &= !(1 << position);
+                self.o &= !(1 << position);
+            }
+        }
+    }
+
+    // Whether `player` owns a full line that passes through `position`.
+    fn has_line_through(&self, position: usize, player: Player) -> bool {
+        let pieces = match player {
+            Player::X => self.x,
+            Player::O => self.o,
+            Player::EMPTY => return false,
+        };
+        WINNING_MASKS.iter()
+            .any(|&mask| mask & (1 << position) != 0 && pieces & mask == mask)
+    }
+
+    fn winner(&self) -> Player {
+        for &mask in WINNING_MASKS.iter() {
+            if self.x & mask == mask {
+                return Player::X;
+            }
+            if self.o & mask == mask {
+                return Player::O;
+            }
+        }
+        Player::EMPTY
+    }
+}
+
+// Terminal values for minimax, always ordered win > draw > loss. A win or
+// loss found `depth` plies below the root scores `win - depth` or
+// `loss + depth`, so quicker wins and slower losses are preferred, but never
+// so far that it reaches the draw score: however slow, a win beats a draw.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+struct SearchScores {
+    win: i32,
+    draw: i32,
+    loss: i32,
+}
+
+impl SearchScores {
+    fn is_ordered(&self) -> bool {
+        self.win > self.draw && self.draw > self.loss
+    }
+
+    fn won_at(&self, depth: i32) -> i32 {
+        self.win.saturating_sub(depth).max(self.draw + 1)
+    }
+
+    fn lost_at(&self, depth: i32) -> i32 {
+        self.loss.saturating_add(depth).min(self.draw - 1)
+    }
+}
+
+impl Default for SearchScores {
+    fn default() -> Self {
+        SearchScores { win: 10, draw: 0, loss: -10 }
+    }
+}
+
+// Probability of the AI blundering. Always clamped to 0.0..=1.0 and never
+// NaN, which is what makes the Eq impl sound.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct MistakeRate(f64);
+
+impl Eq for MistakeRate {}
+
+// State that travels with a board without being part of the game on it,
+// such as the RNG, search counters and JS callbacks. Every Ignored<T> equals
+// every other, so none of it affects board equality.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Ignored<T>(T);
+
+impl<T> PartialEq for Ignored<T> {
+    fn eq(&self, _other: &Ignored<T>) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Ignored<T> {}
+
+// The flag behind stop(). A copy of a board gets a fresh flag rather than
+// sharing this one, so stopping a snapshot or the copy a hint searches never
+// reaches the board it came from.
+#[derive(Debug, Default)]
+struct StopFlag(Arc<AtomicBool>);
+
+impl Clone for StopFlag {
+    fn clone(&self) -> Self {
+        StopFlag::default()
+    }
+}
+
+// Counters from the most recent search, for performance work.
+#[derive(Debug, Clone, Default)]
+struct SearchStats {
+    nodes: u64,
+    stopped: bool,
+}
+
+// JS function registered through set_on_move or set_on_progress. It is held
+// as a closure so native tests can register a Rust one in its place.
+#[derive(Clone, Default)]
+struct JsCallback(Option<Rc<dyn Fn(u32, u32)>>);
+
+impl JsCallback {
+    fn from_function(callback: js_sys::Function) -> Self {
+        JsCallback(Some(Rc::new(move |first, second| {
+            let _ = callback.call2(&JsValue::NULL, &JsValue::from(first), &JsValue::from(second));
+        })))
+    }
+
+    fn call(&self, first: u32, second: u32) {
+        if let Some(callback) = &self.0 {
+            callback(first, second);
+        }
+    }
+}
+
+impl fmt::Debug for JsCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JsCallback({})", if self.0.is_some() { "set" } else { "unset" })
+    }
+}
+
+// The moves get_best_move last found tied for best and everything their
+// choice depended on. They are kept in canonical coordinates, so they still
+// answer after the position is rotated or reflected. Clones share the cache,
+// which is what lets a hint worked out on a copy be reused by the real move.
+type BestMoveCache = Rc<RefCell<Option<CachedMove>>>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct CachedMove {
+    key: u32,
+    turn: Player,
+    size: usize,
+    win_length: usize,
+    max_depth: Option<usize>,
+    search_scores: SearchScores,
+    variant: Variant,
+    early_draw: bool,
+    canonical_moves: Vec<usize>,
+}
+
+// When each move in the history was played, in milliseconds since the epoch,
+// one per entry of `moves`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct MoveTimes {
+    stamps: Vec<f64>,
+    #[serde(skip)]
+    clock: Option<fn() -> f64>,
+}
+
+impl MoveTimes {
+    fn now(&self) -> f64 {
+        match self.clock {
+            Some(clock) => clock(),
+            None => system_time_millis(),
+        }
+    }
 }
 
 #[wasm_bindgen]
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Board {
+    id: String,
+    metadata: HashMap<String, String>,
     matrix: Vec<Player>,
     moves: Vec<usize>,
+    redo_stack: Vec<usize>,
+    move_times: Ignored<MoveTimes>,
     status: State,
+    start_player: Player,
     turn: Player,
     winner: Player,
-    difficulty: Difficulty
+    difficulty: Difficulty,
+    ai_player: Player,
+    weighted_random: bool,
+    early_draw: bool,
+    variant: Variant,
+    size: usize,
+    win_length: usize,
+    max_depth: Option<usize>,
+    search_scores: SearchScores,
+    mistake_rate: Option<MistakeRate>,
+    #[serde(skip)]
+    bitboard: Bitboard,
+    // winning_lines for this size and win length, built once per board since
+    // the search checks them after every move it makes and takes back.
+    #[serde(skip)]
+    lines: Vec<Vec<usize>>,
+    #[serde(skip)]
+    search_stats: Ignored<SearchStats>,
+    #[serde(skip)]
+    best_move_cache: Ignored<BestMoveCache>,
+    #[serde(skip, default = "entropy_rng")]
+    rng: Ignored<StdRng>,
+    #[serde(skip)]
+    on_move: Ignored<JsCallback>,
+    #[serde(skip)]
+    on_progress: Ignored<JsCallback>,
+    // Set by stop() to make the running AI move search, or the next one if
+    // none is running, return early. stop_handle hands the flag out so
+    // another thread can set it while this one is busy searching.
+    #[serde(skip)]
+    cancel: Ignored<StopFlag>
 }
 #[wasm_bindgen]
 impl Board {
 
+    /// An EMPTY `start_player` would never alternate turns, so it is read as
+    /// X here and in the other constructors; use try_new to reject it.
     #[wasm_bindgen(constructor)]
     pub fn new(start_player: Player, difficulty: Difficulty) -> Self {
+        Board::new_sized(start_player, difficulty, 3)
+    }
+
+    #[wasm_bindgen]
+    pub fn try_new(start_player: Player, difficulty: Difficulty) -> Result<Board, JsValue> {
+        if start_player == Player::EMPTY {
+            Err(JsValue::from("Starting Player Cannot Be Empty."))
+        } else {
+            Ok(Board::new(start_player, difficulty))
+        }
+    }
+
+    /// A `size` of 0 has no cells to play in, so it is read as 1.
+    #[wasm_bindgen]
+    pub fn new_sized(start_player: Player, difficulty: Difficulty, size: usize) -> Self {
+        Board::new_with_win_length(start_player, difficulty, size, size)
+    }
+
+    /// `win_length` is clamped to 1..=size, as no other length can ever be
+    /// completed; use try_new_with_win_length to reject such values instead.
+    #[wasm_bindgen]
+    pub fn new_with_win_length(start_player: Player, difficulty: Difficulty, size: usize,
+        win_length: usize) -> Self {
+        let start_player = starting_player(start_player);
+        let size = size.max(1);
+        let win_length = win_length.clamp(1, size);
         Board {
-            matrix: vec![Player::EMPTY, Player::EMPTY, Player::EMPTY,
-                Player::EMPTY, Player::EMPTY, Player::EMPTY,
-                Player::EMPTY, Player::EMPTY, Player::EMPTY
-            ],
+            id: String::new(),
+            metadata: HashMap::new(),
+            matrix: vec![Player::EMPTY; size * size],
             moves: vec![],
+            redo_stack: vec![],
+            move_times: Ignored::default(),
             status: State::INPROGRESS,
+            start_player,
             turn: start_player,
             winner: Player::EMPTY,
-            difficulty: difficulty
+            difficulty,
+            ai_player: Player::EMPTY,
+            weighted_random: false,
+            early_draw: false,
+            variant: Variant::STANDARD,
+            size,
+            win_length,
+            max_depth: None,
+            search_scores: SearchScores::default(),
+            mistake_rate: None,
+            bitboard: Bitboard::default(),
+            lines: winning_lines(size, win_length),
+            search_stats: Ignored::default(),
+            best_move_cache: Ignored::default(),
+            rng: entropy_rng(),
+            on_move: Ignored::default(),
+            on_progress: Ignored::default(),
+            cancel: Ignored::default()
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn try_new_with_win_length(start_player: Player, difficulty: Difficulty, size: usize,
+        win_length: usize) -> Result<Board, JsValue> {
+        if size == 0 {
+            Err(JsValue::from("Board Size Must Be At Least 1."))
+        } else if win_length == 0 || win_length > size {
+            Err(JsValue::from("Win Length Must Be Between 1 And The Board Size."))
+        } else {
+            Ok(Board::new_with_win_length(start_player, difficulty, size, win_length))
+        }
+    }
+
+    /// Like new, but tags the board with `id` so apps running several games
+    /// can tell them apart; boards made any other way have an empty id.
+    #[wasm_bindgen]
+    pub fn new_with_id(start_player: Player, difficulty: Difficulty, id: &str) -> Self {
+        let mut board = Board::new(start_player, difficulty);
+        board.id = id.to_string();
+        board
+    }
+
+    #[wasm_bindgen]
+    pub fn new_seeded(start_player: Player, difficulty: Difficulty, seed: u64) -> Self {
+        let mut board = Board::new(start_player, difficulty);
+        board.rng = Ignored(StdRng::seed_from_u64(seed));
+        board
+    }
+
+    #[wasm_bindgen]
+    pub fn reset(&mut self, start_player: Player) {
+        let start_player = starting_player(start_player);
+        self.matrix = vec![Player::EMPTY; self.size * self.size];
+        self.bitboard = Bitboard::default();
+        self.moves.clear();
+        self.redo_stack.clear();
+        self.move_times.0.stamps.clear();
+        self.status = State::INPROGRESS;
+        self.start_player = start_player;
+        self.turn = start_player;
+        self.winner = Player::EMPTY;
+    }
+
+    #[wasm_bindgen]
+    pub fn get_id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// Attaches free-form data such as player names; it is kept across
+    /// moves and resets and saved by to_json.
+    #[wasm_bindgen]
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.insert(key.to_string(), value.to_string());
+    }
+
+    #[wasm_bindgen]
+    pub fn get_metadata(&self, key: &str) -> Option<String> {
+        self.metadata.get(key).cloned()
+    }
+
+    #[wasm_bindgen]
+    pub fn snapshot(&self) -> Board {
+        self.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn restore(&mut self, other: &Board) {
+        *self = other.clone();
+    }
+
+    /// Starts a new game with the other player opening, or the same one if
+    /// the last game was drawn. Settings such as the difficulty are kept.
+    #[wasm_bindgen]
+    pub fn rematch(&mut self) {
+        let start_player = if self.status == State::DRAW {
+            self.start_player
+        } else {
+            opponent(self.start_player)
+        };
+        self.reset(start_player);
+    }
+
+    #[wasm_bindgen]
+    pub fn get_start_player(&self) -> Player {
+        self.start_player
+    }
+
+    /// Jumps to the position after the first `n` moves of the game. Moves
+    /// past that point stay on the redo stack, so scrubbing forward works too.
+    /// Like redo, replaying stops early at a move that is no longer legal.
+    #[wasm_bindgen]
+    pub fn go_to_move(&mut self, n: usize) -> Result<(), JsValue> {
+        let mut history: Vec<usize> = self.moves.clone();
+        history.extend(self.redo_stack.iter().rev());
+        if n > history.len() {
+            return Err(JsValue::from("Move Number Out Of Range."));
+        }
+
+        let mut stamps = self.move_times.0.stamps.clone();
+        self.reset(self.start_player);
+        let mut played = 0;
+        for &move_position in history[..n].iter() {
+            if self.check_move(move_position).is_err() {
+                break;
+            }
+            self.apply_move(move_position);
+            played += 1;
+        }
+        // Moves brought back from the redo stack are timed as played now.
+        let now = self.move_times.0.now();
+        stamps.resize(played, now);
+        self.move_times.0.stamps = stamps;
+        self.redo_stack = history[played..].iter().rev().cloned().collect();
+        Ok(())
+    }
+
+    /// The player who made move `n` of the history, counting from 0.
+    #[wasm_bindgen]
+    pub fn get_player_at_turn(&self, n: usize) -> Result<Player, JsValue> {
+        if n >= self.moves.len() {
+            return Err(JsValue::from("Move Number Out Of Range."));
         }
+
+        Ok(if n.is_multiple_of(2) { self.start_player } else { opponent(self.start_player) })
     }
 
     #[wasm_bindgen]
     pub fn get_current_turn(&self) -> Player {
-        self.turn.clone()
+        self.turn
+    }
+
+    /// get_current_turn as its discriminant (EMPTY 0, X 1, O 2), for
+    /// bindings that handle plain numbers more reliably than enums.
+    #[wasm_bindgen]
+    pub fn get_current_turn_code(&self) -> i32 {
+        self.turn as i32
+    }
+
+    #[wasm_bindgen]
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty.clone()
+    }
+
+    /// Whichever of set_difficulty and set_mistake_rate was called last
+    /// decides how the AI plays, so this clears any mistake rate.
+    #[wasm_bindgen]
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.mistake_rate = None;
+    }
+
+    #[wasm_bindgen]
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    #[wasm_bindgen]
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        self.recompute_status();
+    }
+
+    #[wasm_bindgen]
+    pub fn make_move(&mut self, move_position: usize) -> Result<(), MoveError> {
+        self.check_move(move_position)?;
+        self.apply_move(move_position);
+        self.redo_stack.clear();
+        self.record_move_time();
+        self.notify_move(move_position);
+        Ok(())
+    }
+
+    /// Makes make_move_and_respond answer as `player`; EMPTY turns the AI off.
+    #[wasm_bindgen]
+    pub fn set_ai_player(&mut self, player: Player) {
+        self.ai_player = player;
+    }
+
+    /// Plays the human move at `pos` and, when that leaves the AI player to
+    /// move in a game still in progress, plays and returns the AI's reply.
+    #[wasm_bindgen]
+    pub fn make_move_and_respond(&mut self, pos: usize) -> Result<Option<usize>, JsValue> {
+        self.make_move(pos)?;
+        if self.status != State::INPROGRESS || self.ai_player == Player::EMPTY
+            || self.turn != self.ai_player {
+            return Ok(None);
+        }
+
+        match self.get_next_move() {
+            Some(reply) => {
+                self.make_move(reply)?;
+                Ok(Some(reply))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// make_move that also returns the state the move left the game in.
+    #[wasm_bindgen]
+    pub fn make_move_status(&mut self, move_position: usize) -> Result<State, MoveError> {
+        self.make_move(move_position)?;
+        Ok(self.status)
+    }
+
+    fn record_move_time(&mut self) {
+        let now = self.move_times.0.now();
+        self.move_times.0.stamps.push(now);
     }
 
-    #[wasm_bindgen(catch)]
-    pub fn make_move(&mut self, move_position: usize) -> Result<(), JsValue> {
-        if move_position > 8 {
-            Err(JsValue::from("Illegal Position Supplied. Try Again."))
+    /// When each move in get_move_history was played, in milliseconds since
+    /// the epoch. Moves whose time is unknown, such as those reconstructed by
+    /// from_matrix, are stamped 0.
+    #[wasm_bindgen]
+    pub fn get_move_timestamps(&self) -> Vec<f64> {
+        self.move_times.0.stamps.clone()
+    }
+
+    /// Registers `callback` to be called as `callback(position, status)` after
+    /// every successful make_move, with `status` the `State` discriminant.
+    #[wasm_bindgen]
+    pub fn set_on_move(&mut self, callback: js_sys::Function) {
+        self.on_move = Ignored(JsCallback::from_function(callback));
+    }
+
+    fn notify_move(&self, move_position: usize) {
+        self.on_move.0.call(move_position as u32, self.status as u32);
+    }
+
+    // Places the piece without validation or touching the redo stack; the
+    // search uses this together with revert_move.
+    fn apply_move(&mut self, move_position: usize) {
+        self.matrix[move_position] = self.turn;
+        if self.uses_bitboard() {
+            self.bitboard.set(move_position, self.turn);
+        }
+        self.moves.push(move_position);
+        self.change_turn();
+        self.change_board_state();
+    }
+
+    #[wasm_bindgen]
+    pub fn try_move(&self, pos: usize) -> Result<Board, MoveError> {
+        let mut board = self.clone();
+        board.on_move = Ignored::default();
+        board.make_move(pos)?;
+        Ok(board)
+    }
+
+    #[wasm_bindgen]
+    pub fn is_legal_move(&self, pos: usize) -> bool {
+        self.check_move(pos).is_ok()
+    }
+
+    // Why make_move would refuse `move_position`, if it would.
+    fn check_move(&self, move_position: usize) -> Result<(), MoveError> {
+        if self.status != State::INPROGRESS {
+            Err(MoveError::GameOver)
+        } else if move_position >= self.matrix.len() {
+            Err(MoveError::OutOfRange)
         } else if self.moves.contains(&move_position) {
-            Err(JsValue::from("Position Already Filled. Try Again"))
+            Err(MoveError::AlreadyFilled)
         } else {
-            self.matrix[move_position] = self.turn.clone();
-            self.moves.push(move_position);
-            self.change_turn();
-            self.change_board_state();
             Ok(())
         }
     }
 
-    fn undo_move(&mut self) {
-        let move_position = self.moves.pop().unwrap();
+    #[wasm_bindgen]
+    pub fn make_move_rc(&mut self, row: usize, col: usize) -> Result<(), MoveError> {
+        if row >= self.size || col >= self.size {
+            Err(MoveError::OutOfRange)
+        } else {
+            self.make_move(row * self.size + col)
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn get_cell(&self, row: usize, col: usize) -> Result<Player, JsValue> {
+        if row >= self.size || col >= self.size {
+            Err(JsValue::from("Cell Out Of Range."))
+        } else {
+            Ok(self.matrix[row * self.size + col])
+        }
+    }
+
+    /// Plays the cell named in move_to_notation's notation, with the columns
+    /// and rows running as far as this board is wide.
+    #[wasm_bindgen]
+    pub fn make_move_notation(&mut self, notation: &str) -> Result<(), JsValue> {
+        let move_position = parse_notation(notation, self.size)
+            .ok_or_else(|| JsValue::from("Illegal Notation Supplied. Try Again."))?;
+        self.make_move(move_position).map_err(JsValue::from)
+    }
+
+    #[wasm_bindgen]
+    pub fn undo_move(&mut self) -> Result<usize, JsValue> {
+        let move_position = self.revert_move().ok_or_else(|| JsValue::from("No Moves To Undo."))?;
+        self.redo_stack.push(move_position);
+        self.move_times.0.stamps.pop();
+        Ok(move_position)
+    }
+
+    #[wasm_bindgen]
+    pub fn redo(&mut self) -> Result<usize, JsValue> {
+        let move_position = match self.redo_stack.last() {
+            Some(&move_position) => move_position,
+            None => return Err(JsValue::from("No Moves To Redo.")),
+        };
+        self.check_move(move_position)?;
+        self.redo_stack.pop();
+        self.apply_move(move_position);
+        self.record_move_time();
+        Ok(move_position)
+    }
+
+    fn revert_move(&mut self) -> Option<usize> {
+        let move_position = self.moves.pop()?;
         self.matrix[move_position] = Player::EMPTY;
+        if self.uses_bitboard() {
+            self.bitboard.set(move_position, Player::EMPTY);
+        }
         self.change_turn();
-        self.change_board_state();
+        self.recompute_status();
+        Some(move_position)
     }
 
     fn change_turn(&mut self) {
-        self.turn = match self.turn {
-            Player::X => Player::O,
-            Player::O => Player::X,
-            _ => self.turn.clone(),
-        };
+        self.turn = opponent(self.turn);
     }
 
     fn change_board_state(&mut self) {
         let len: usize = self.moves.len();
         if len == 0 {
-            return ();
+            self.status = State::INPROGRESS;
+            self.winner = Player::EMPTY;
+            return;
         }
 
         let &move_position = self.moves.get(len - 1).unwrap();
 
-        let row: usize = move_position.div_euclid(3);
-        let col: usize = move_position.rem_euclid(3);
+        // Only lines through the last move can have just been completed.
+        let line_complete = if self.uses_bitboard() {
+            self.bitboard.has_line_through(move_position, self.matrix[move_position])
+        } else {
+            let row_complete = self.run_length(move_position, 0, 1) >= self.win_length;
 
-        let row_complete = self.matrix[move_position] == self.matrix[row * 3]
-            && self.matrix[row * 3] == self.matrix[row * 3 + 1]
-            && self.matrix[row * 3 + 1] == self.matrix[row * 3 + 2];
+            let col_complete = self.run_length(move_position, 1, 0) >= self.win_length;
 
-        let col_complete = self.matrix[move_position] == self.matrix[col]
-            && self.matrix[col] == self.matrix[col + 3]
-            && self.matrix[col + 3] == self.matrix[col + 6];
+            let main_diag_complete = self.run_length(move_position, 1, 1) >= self.win_length;
 
-        let main_diag_complete = self.matrix[move_position] == self.matrix[0]
-            && self.matrix[0] == self.matrix[4]
-            && self.matrix[4] == self.matrix[8];
+            let sec_diag_complete = self.run_length(move_position, 1, -1) >= self.win_length;
 
-        let sec_diag_complete = self.matrix[move_position] == self.matrix[2]
-            && self.matrix[2] == self.matrix[4]
-            && self.matrix[4] == self.matrix[6];
+            row_complete || col_complete || main_diag_complete || sec_diag_complete
+        };
 
-        if row_complete || col_complete || main_diag_complete || sec_diag_complete {
+        if line_complete {
             self.status = State::RESULTED;
-            self.winner = self.matrix[move_position].clone();
-        } else if self.moves.len() >= 9 {
+            self.winner = self.line_winner(self.matrix[move_position]);
+        } else if self.is_drawn() {
             self.status = State::DRAW;
         } else {
             self.status = State::INPROGRESS;
         }
     }
 
-    #[wasm_bindgen]
-    pub fn get_next_move(&mut self) -> usize {
-        match self.difficulty {
-            Difficulty::EASY => self.get_random_move(),
-            Difficulty::MEDIUM => self.get_medium_move(),
-            Difficulty::DIFFICULT => self.get_best_move(),
+    // Length of the run of identical pieces through `position` along the
+    // direction (d_row, d_col), counting both ways and the cell itself.
+    fn run_length(&self, position: usize, d_row: isize, d_col: isize) -> usize {
+        let size = self.size as isize;
+        let row = position as isize / size;
+        let col = position as isize % size;
+        let mut length = 1;
+
+        for &sign in [1, -1].iter() {
+            let (mut r, mut c) = (row + sign * d_row, col + sign * d_col);
+            while r >= 0 && r < size && c >= 0 && c < size
+                && self.matrix[(r * size + c) as usize] == self.matrix[position] {
+                length += 1;
+                r += sign * d_row;
+                c += sign * d_col;
+            }
         }
+
+        length
     }
 
-    pub fn get_random_move(&self) -> usize {
-        let mut rng = rand::thread_rng();
-        let available_moves = find_available_moves(self);
-        let move_index = rng.gen_range(0, available_moves.len());
-        available_moves[move_index]
+    fn find_completed_line(&self) -> Option<Vec<usize>> {
+        self.lines.iter().find(|line| {
+            let first = &self.matrix[line[0]];
+            first != &Player::EMPTY && line.iter().all(|&cell| &self.matrix[cell] == first)
+        }).cloned()
     }
 
-    pub fn get_medium_move(&mut self) -> usize {
-        let mut rng = rand::thread_rng();
-        let random_num = rng.gen_range(0, 100);
-        if random_num < 75 {
-            self.get_best_move()
-        } else {
-            self.get_random_move()
-        }
+    fn uses_bitboard(&self) -> bool {
+        self.size == 3 && self.win_length == 3
     }
 
-    pub fn get_best_move(&mut self) -> usize {
-        let mut best_score = -1000;
-        let mut best_move: usize = 0;
-        for mv in find_available_moves(self) {
-            self.make_move(mv);
-            let score = minimax(self, &self.turn.clone());
-            if score > best_score {
-                best_score = score;
-                best_move = mv;
+    fn sync_bitboard(&mut self) {
+        self.bitboard = Bitboard::default();
+        if self.uses_bitboard() {
+            for (position, &player) in self.matrix.iter().enumerate() {
+                self.bitboard.set(position, player);
             }
-            self.undo_move();
         }
+    }
 
-        best_move
+    // Who wins when `owner` completes a line under the current variant.
+    fn line_winner(&self, owner: Player) -> Player {
+        match self.variant {
+            Variant::STANDARD => owner,
+            Variant::MISERE => opponent(owner),
+        }
     }
 
-    #[wasm_bindgen]
-    pub fn get_board_state(&self) -> State {
-        self.status.clone()
+    // Called once no line is complete: the game is drawn when there is no
+    // move left, or, with early draws on, when no line can be completed.
+    fn is_drawn(&self) -> bool {
+        self.is_board_full() || (self.early_draw && self.is_dead_draw())
+    }
+
+    fn recompute_status(&mut self) {
+        let (status, winner) = self.computed_status();
+        self.status = status;
+        self.winner = winner;
+    }
+
+    // The status and winner the pieces on the board call for.
+    fn computed_status(&self) -> (State, Player) {
+        let owner = if self.uses_bitboard() {
+            self.bitboard.winner()
+        } else {
+            self.find_completed_line().map_or(Player::EMPTY, |line| self.matrix[line[0]])
+        };
+        if owner != Player::EMPTY {
+            (State::RESULTED, self.line_winner(owner))
+        } else if self.is_drawn() {
+            (State::DRAW, Player::EMPTY)
+        } else {
+            (State::INPROGRESS, Player::EMPTY)
+        }
     }
 
     #[wasm_bindgen]
-    pub fn get_winner(&self) -> Player {
-        self.winner.clone()
+    pub fn get_next_move(&mut self) -> Option<usize> {
+        if let Some(MistakeRate(rate)) = self.mistake_rate {
+            let blunder = rate >= 1.0 || (rate > 0.0 && self.rng.0.gen_bool(rate));
+            return if blunder { self.get_random_move() } else { self.get_best_move() };
+        }
+
+        match self.difficulty {
+            Difficulty::EASY => self.get_random_move(),
+            Difficulty::MEDIUM => self.get_medium_move(),
+            Difficulty::DIFFICULT => self.get_best_move(),
+            Difficulty::IMPOSSIBLE => self.get_impossible_move(),
+        }
     }
-}
 
-fn find_available_moves(board: &Board) -> Vec<usize> {
-    let mut available_moves: Vec<usize> = vec![];
+    #[wasm_bindgen]
+    pub fn get_next_move_checked(&mut self) -> Result<usize, MoveError> {
+        self.get_next_move().ok_or(MoveError::GameOver)
+    }
 
-    for (index, player) in board.matrix.iter().enumerate() {
-        if player == &Player::EMPTY {
-            available_moves.push(index);
+    #[wasm_bindgen]
+    pub fn get_hint(&self) -> Option<usize> {
+        // Search a copy so the hint can never leave a trace on the real game.
+        self.clone().get_next_move()
+    }
+
+    pub fn get_random_move(&mut self) -> Option<usize> {
+        if self.status != State::INPROGRESS {
+            return None;
+        }
+        let available_moves = find_available_moves(self);
+        if self.weighted_random {
+            let total: i32 = available_moves.iter().map(|&mv| self.cell_value(mv)).sum();
+            let mut pick = self.rng.0.gen_range(0, total);
+            for &mv in available_moves.iter() {
+                pick -= self.cell_value(mv);
+                if pick < 0 {
+                    return Some(mv);
+                }
+            }
         }
+        let move_index = self.rng.0.gen_range(0, available_moves.len());
+        Some(available_moves[move_index])
     }
 
-    available_moves
-}
+    /// With `enabled` set, the game is drawn as soon as every line holds both
+    /// an X and an O instead of only once the board is full.
+    #[wasm_bindgen]
+    pub fn set_early_draw(&mut self, enabled: bool) {
+        self.early_draw = enabled;
+        self.recompute_status();
+    }
 
-fn minimax(board: &mut Board, mover: &Player) -> i32 {
-    if board.status == State::RESULTED {
-        if &board.winner != mover { return 1; } else { return -1; };
-    } else if board.status == State::DRAW { return 0; }
+    /// With `weighted` set, random moves favour cells by their positional
+    /// value (center 3, corners 2, edges 1) instead of being uniform.
+    #[wasm_bindgen]
+    pub fn set_weighted_random(&mut self, weighted: bool) {
+        self.weighted_random = weighted;
+    }
 
-    let is_max = &board.turn != mover;
-    let mut best_score = if is_max{ -1000 } else { 1000 };
-    for mv in find_available_moves(&board) {
-        board.make_move(mv);
-        let score = minimax(board, mover);
-        if is_max && score > best_score {
-            best_score = score;
+    pub fn get_medium_move(&mut self) -> Option<usize> {
+        // Completing a line loses under misere, so the one-ply tactics only
+        // apply to the standard game.
+        if self.variant == Variant::STANDARD {
+            let winning_move = self.find_winning_move(self.turn);
+            if winning_move.is_some() {
+                return winning_move;
+            }
+            let blocking_move = self.find_blocking_move();
+            if blocking_move.is_some() {
+                return blocking_move;
+            }
         }
-        if !is_max && score < best_score {
-            best_score = score;
+
+        let random_num = self.rng.0.gen_range(0, 100);
+        if random_num < 75 {
+            self.get_best_move()
+        } else {
+            self.get_random_move()
         }
-        board.undo_move();
     }
 
-    best_score
+    pub fn get_best_move(&mut self) -> Option<usize> {
+        let on_progress = self.on_progress.0.clone();
+        self.get_best_move_with_progress(&mut |done, total| {
+            on_progress.call(done as u32, total as u32)
+        })
+    }
+
+    /// Registers `callback` to be called as `callback(done, total)` by
+    /// get_best_move each time it finishes scoring one of the `total` legal
+    /// moves, for a progress indicator on slow searches.
+    #[wasm_bindgen]
+    pub fn set_on_progress(&mut self, callback: js_sys::Function) {
+        self.on_progress = Ignored(JsCallback::from_function(callback));
+    }
+
+    #[wasm_bindgen]
+    pub fn get_best_move_checked(&mut self) -> Result<usize, MoveError> {
+        if self.status != State::INPROGRESS {
+            return Err(MoveError::GameOver);
+        }
+
+        self.get_best_move().ok_or(MoveError::GameOver)
+    }
+
+    /// get_best_move searching at most `max_depth` plies; 0 is read as 1.
+    #[wasm_bindgen]
+    pub fn get_best_move_depth(&mut self, max_depth: usize) -> Option<usize> {
+        let scores = self.search_moves_reporting(Some(max_depth), self.cancel.0.0.clone(),
+            &mut |_, _| {});
+        self.pick_best_move(scores)
+    }
+
+    /// Overrides the difficulty tiers: from now on the AI plays the best move
+    /// except with probability `rate`, when it plays a random legal one. A
+    /// later set_difficulty switches back to the tiers.
+    #[wasm_bindgen]
+    pub fn set_mistake_rate(&mut self, rate: f64) {
+        // clamp passes NaN through, so it is mapped to 0 first.
+        let rate = if rate.is_nan() { 0.0 } else { rate.clamp(0.0, 1.0) };
+        self.mistake_rate = Some(MistakeRate(rate));
+    }
+
+    /// Values minimax gives a won, drawn and lost game, 10, 0 and -10 by
+    /// default, which must satisfy win > draw > loss. Wins count down and
+    /// losses up by one per ply searched, stopping short of the draw score.
+    #[wasm_bindgen]
+    pub fn set_search_scores(&mut self, win: i32, draw: i32, loss: i32) -> Result<(), JsValue> {
+        let scores = SearchScores { win, draw, loss };
+        if !scores.is_ordered() {
+            return Err(JsValue::from("Search Scores Must Order Win Above Draw Above Loss."));
+        }
+        self.search_scores = scores;
+        Ok(())
+    }
+
+    /// Asks the get_best_move or get_next_move search in progress to return as
+    /// soon as possible with the best move it has so far. If no such search is
+    /// running, the next one stops straight away instead. Analysis such as
+    /// evaluate_moves or theoretical_result always searches in full.
+    #[wasm_bindgen]
+    pub fn stop(&self) {
+        self.cancel.0.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Number of positions minimax visited during the most recent search.
+    #[wasm_bindgen]
+    pub fn last_search_nodes(&self) -> u64 {
+        self.search_stats.0.nodes
+    }
+
+    /// Limits later searches to `max_depth` plies, or lifts the limit with
+    /// None. A limit of 0 is read as 1, since no move can be judged without
+    /// looking at it.
+    #[wasm_bindgen]
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Optimal like get_best_move, but among equally good moves it plays a
+    /// fork first, then the center, then a corner.
+    pub fn get_impossible_move(&mut self) -> Option<usize> {
+        let scores = self.search_moves_reporting(self.max_depth, self.cancel.0.0.clone(),
+            &mut |_, _| {});
+        let best_moves = best_scoring_moves(&scores);
+        if best_moves.is_empty() {
+            return None;
+        }
+
+        let forks = self.fork_moves(self.turn);
+        if let Some(&mv) = best_moves.iter().find(|mv| forks.contains(mv)) {
+            return Some(mv);
+        }
+
+        let size = self.size;
+        let center = (size / 2) * size + size / 2;
+        if size % 2 == 1 && best_moves.contains(&center) {
+            return Some(center);
+        }
+
+        let corners = [0, size - 1, size * (size - 1), size * size - 1];
+        let best_corners: Vec<usize> = best_moves.iter().cloned()
+            .filter(|mv| corners.contains(mv))
+            .collect();
+        if best_corners.is_empty() {
+            self.pick_best_move(scores)
+        } else {
+            let move_index = self.rng.0.gen_range(0, best_corners.len());
+            Some(best_corners[move_index])
+        }
+    }
+
+    fn pick_best_move(&mut self, scores: Vec<(usize, i32)>) -> Option<usize> {
+        let best_moves = best_scoring_moves(&scores);
+        if best_moves.is_empty() {
+            return None;
+        }
+
+        let move_index = self.rng.0.gen_range(0, best_moves.len());
+        Some(best_moves[move_index])
+    }
+
+    /// The line of play from here when both sides play optimally, ending
+    /// with the move that finishes the game.
+    #[wasm_bindgen]
+    pub fn principal_variation(&mut self) -> Vec<usize> {
+        let mut variation: Vec<usize> = vec![];
+        while let Some(&mv) = best_scoring_moves(&self.evaluate_moves()).first() {
+            self.apply_move(mv);
+            variation.push(mv);
+        }
+        for _ in 0..variation.len() {
+            self.revert_move();
+        }
+
+        variation
+    }
+
+    /// Every legal move that keeps the best score available to the side to
+    /// move, in board order.
+    #[wasm_bindgen]
+    pub fn optimal_moves(&mut self) -> Vec<usize> {
+        best_scoring_moves(&self.evaluate_moves())
+    }
+
+    /// Legal moves ordered from best to worst for the side to move; moves
+    /// with equal scores keep their board order.
+    #[wasm_bindgen]
+    pub fn ranked_moves(&mut self) -> Vec<usize> {
+        let mut scores = self.evaluate_moves();
+        scores.sort_by_key(|&(_, score)| Reverse(score));
+        scores.into_iter().map(|(mv, _)| mv).collect()
+    }
+
+    /// How much worse the last move scored than the best move available at
+    /// that point, from the view of whoever played it; 0 means it was optimal.
+    #[wasm_bindgen]
+    pub fn evaluate_last_move(&mut self) -> i32 {
+        let last_move = match self.revert_move() {
+            Some(last_move) => last_move,
+            None => return 0,
+        };
+
+        let scores = self.evaluate_moves();
+        self.apply_move(last_move);
+        let best_score = scores.iter().map(|&(_, score)| score).max().unwrap_or(0);
+        let played_score = scores.iter()
+            .find(|&&(mv, _)| mv == last_move)
+            .map_or(best_score, |&(_, score)| score);
+        best_score - played_score
+    }
+
+    /// Searches to the end of the game, ignoring any depth limit, and reports
+    /// whether perfect play from here by both sides ends in a draw.
+    #[wasm_bindgen]
+    pub fn is_theoretically_drawn(&mut self) -> bool {
+        match self.status {
+            State::DRAW => true,
+            State::RESULTED => false,
+            State::INPROGRESS => self.perfect_play_score() == 0,
+        }
+    }
+
+    /// The player who wins from here under perfect play, or EMPTY when the
+    /// position is a forced draw.
+    #[wasm_bindgen]
+    pub fn theoretical_result(&mut self) -> Player {
+        if self.status != State::INPROGRESS {
+            return self.winner;
+        }
+
+        let score = self.perfect_play_score();
+        if score > 0 {
+            self.turn
+        } else if score < 0 {
+            opponent(self.turn)
+        } else {
+            Player::EMPTY
+        }
+    }
+
+    /// Plies left until the game ends under perfect play by both sides, or
+    /// None once it is over. The winner takes the quickest win and the loser
+    /// holds out longest; a forced draw follows principal_variation, which
+    /// with early draws on can end before the board is full.
+    #[wasm_bindgen]
+    pub fn distance_to_result(&mut self) -> Option<usize> {
+        if self.status != State::INPROGRESS {
+            return None;
+        }
+
+        // A game decided `depth` plies after the root move scores
+        // `win - depth` or `loss + depth`.
+        let scores = SearchScores::default();
+        let score = self.perfect_play_score();
+        let depth = if score > scores.draw {
+            scores.win - score
+        } else if score < scores.draw {
+            score - scores.loss
+        } else {
+            // Draws score the same at any depth, so play the line out.
+            return Some(self.under_perfect_play(|board| board.principal_variation().len()));
+        };
+        Some(depth as usize + 1)
+    }
+
+    // Full-depth minimax value of an in-progress position for the side to
+    // move.
+    fn perfect_play_score(&mut self) -> i32 {
+        self.under_perfect_play(|board| {
+            board.search_moves(None).iter().map(|&(_, score)| score).max().unwrap_or(0)
+        })
+    }
+
+    // Runs `f` with the depth limit lifted and the default scores, so that 0
+    // always means a draw, then puts both settings back.
+    fn under_perfect_play<R>(&mut self, f: impl FnOnce(&mut Board) -> R) -> R {
+        let (max_depth, search_scores) = (self.max_depth, self.search_scores);
+        self.max_depth = None;
+        self.search_scores = SearchScores::default();
+        let result = f(self);
+        self.max_depth = max_depth;
+        self.search_scores = search_scores;
+        result
+    }
+
+    /// True when the side to move can force a win within `n` of its own
+    /// moves however the opponent defends. The board is left as it was.
+    #[wasm_bindgen]
+    pub fn forced_win_in(&mut self, n: usize) -> bool {
+        if self.status != State::INPROGRESS {
+            return false;
+        }
+        let player = self.turn;
+        self.wins_within(player, n)
+    }
+
+    // With `player` to move, whether some move wins on the spot or leaves
+    // every reply in a position `player` still wins within `n - 1` moves.
+    fn wins_within(&mut self, player: Player, n: usize) -> bool {
+        if n == 0 {
+            return false;
+        }
+
+        for move_position in self.get_available_moves() {
+            self.apply_move(move_position);
+            let wins = match self.status {
+                State::RESULTED => self.winner == player,
+                State::DRAW => false,
+                State::INPROGRESS => n > 1 && self.get_available_moves().into_iter().all(|reply| {
+                    self.apply_move(reply);
+                    let holds = match self.status {
+                        State::RESULTED => self.winner == player,
+                        State::DRAW => false,
+                        State::INPROGRESS => self.wins_within(player, n - 1),
+                    };
+                    self.revert_move();
+                    holds
+                }),
+            };
+            self.revert_move();
+            if wins {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// A cell that would win the game for `player` if filled now. None under
+    /// MISERE, where completing a line loses.
+    #[wasm_bindgen]
+    pub fn find_winning_move(&self, player: Player) -> Option<usize> {
+        if self.status != State::INPROGRESS {
+            return None;
+        }
+
+        self.winning_cells(player).into_iter().next()
+    }
+
+    /// A cell the opponent of the side to move would win on next turn.
+    #[wasm_bindgen]
+    pub fn find_blocking_move(&self) -> Option<usize> {
+        self.find_winning_move(opponent(self.turn))
+    }
+
+    #[wasm_bindgen]
+    pub fn find_fork_move(&mut self, player: Player) -> Option<usize> {
+        if self.status != State::INPROGRESS {
+            return None;
+        }
+
+        self.fork_moves(player).into_iter().next()
+    }
+
+    /// How many empty cells would give `player` two or more winning threats;
+    /// always 0 under MISERE, which has no winning threats.
+    #[wasm_bindgen]
+    pub fn fork_count(&self, player: Player) -> usize {
+        if self.status != State::INPROGRESS {
+            return 0;
+        }
+
+        // fork_moves tries each cell in place, so it runs on a scratch copy.
+        self.clone().fork_moves(player).len()
+    }
+
+    // Empty cells that would leave `player` with two or more winning threats.
+    fn fork_moves(&mut self, player: Player) -> Vec<usize> {
+        let mut forks: Vec<usize> = vec![];
+        for mv in find_available_moves(self) {
+            self.matrix[mv] = player;
+            let threats = self.winning_cells(player).len();
+            self.matrix[mv] = Player::EMPTY;
+            if threats >= 2 {
+                forks.push(mv);
+            }
+        }
+
+        forks
+    }
+
+    /// Every empty cell that would win the game for `player` if filled now;
+    /// two or more entries mean `player` has a fork. Empty under MISERE,
+    /// where completing a line loses.
+    #[wasm_bindgen]
+    pub fn threats(&self, player: Player) -> Vec<usize> {
+        if self.status != State::INPROGRESS {
+            return vec![];
+        }
+
+        self.winning_cells(player)
+    }
+
+    /// Per-cell overlay for the side to move: 1 where a piece would block an
+    /// opponent win, 2 where it would win and 3 where it would create a fork,
+    /// reporting the largest code that applies. Other cells are 0, as is every
+    /// cell under MISERE.
+    #[wasm_bindgen]
+    pub fn threat_map(&self) -> Vec<i32> {
+        let mut codes = vec![0; self.matrix.len()];
+        if self.status != State::INPROGRESS {
+            return codes;
+        }
+
+        for mv in self.winning_cells(opponent(self.turn)) {
+            codes[mv] = 1;
+        }
+        for mv in self.winning_cells(self.turn) {
+            codes[mv] = 2;
+        }
+        for mv in self.clone().fork_moves(self.turn) {
+            codes[mv] = 3;
+        }
+        codes
+    }
+
+    /// Occupancy value for `player` minus the same for the opponent: 3 for
+    /// the center, 2 for each corner and 1 for every other cell.
+    #[wasm_bindgen]
+    pub fn positional_score(&self, player: Player) -> i32 {
+        if player == Player::EMPTY {
+            return 0;
+        }
+        let other = opponent(player);
+        self.matrix.iter().enumerate().map(|(index, cell)| {
+            if cell == &player {
+                self.cell_value(index)
+            } else if cell == &other {
+                -self.cell_value(index)
+            } else {
+                0
+            }
+        }).sum()
+    }
+
+    fn cell_value(&self, index: usize) -> i32 {
+        let size = self.size;
+        let center = (size / 2) * size + size / 2;
+        let corners = [0, size - 1, size * (size - 1), size * size - 1];
+        if size % 2 == 1 && index == center {
+            3
+        } else if corners.contains(&index) {
+            2
+        } else {
+            1
+        }
+    }
+
+    // Empty cells that would win the game for `player` if filled now. Under
+    // MISERE completing a line hands the win to the opponent, so there are
+    // none.
+    fn winning_cells(&self, player: Player) -> Vec<usize> {
+        if self.line_winner(player) != player {
+            return vec![];
+        }
+
+        find_available_moves(self).into_iter().filter(|&mv| {
+            self.lines.iter().any(|line| line.contains(&mv)
+                && line.iter().all(|&cell| cell == mv || self.matrix[cell] == player))
+        }).collect()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_board_state(&self) -> State {
+        self.status
+    }
+
+    #[wasm_bindgen]
+    pub fn is_game_over(&self) -> bool {
+        self.status == State::DRAW || self.status == State::RESULTED
+    }
+
+    /// True once every line holds both an X and an O, so neither player can
+    /// win any more even though the board may not be full.
+    #[wasm_bindgen]
+    pub fn is_dead_draw(&self) -> bool {
+        self.open_lines().iter().all(|&count| count == 0)
+    }
+
+    /// How many lines each player can still complete, as [x_open, o_open]. A
+    /// line is open to a player while it holds none of the opponent's pieces.
+    #[wasm_bindgen]
+    pub fn open_lines(&self) -> Vec<usize> {
+        let open_to = |player: Player| self.lines.iter()
+            .filter(|line| line.iter().all(|&cell| self.matrix[cell] != opponent(player)))
+            .count();
+        vec![open_to(Player::X), open_to(Player::O)]
+    }
+
+    #[wasm_bindgen]
+    pub fn get_winner(&self) -> Player {
+        self.winner
+    }
+
+    /// get_winner as its discriminant (EMPTY 0, X 1, O 2).
+    #[wasm_bindgen]
+    pub fn get_winner_code(&self) -> i32 {
+        self.winner as i32
+    }
+
+    #[wasm_bindgen]
+    pub fn get_result_message(&self) -> String {
+        match (self.status, self.winner) {
+            (State::RESULTED, Player::X) => String::from("X wins"),
+            (State::RESULTED, Player::O) => String::from("O wins"),
+            (State::DRAW, _) => String::from("Draw"),
+            _ => String::from("In progress"),
+        }
+    }
+
+    /// Cells in row-major order, so index `row * size + col` with 0 at the top
+    /// left (0..8 on the default 3x3 board).
+    #[wasm_bindgen]
+    pub fn get_matrix(&self) -> Vec<Player> {
+        self.matrix.clone()
+    }
+
+    /// get_matrix with each cell as its `Player` discriminant (EMPTY 0, X 1,
+    /// O 2), for bindings that handle plain numbers more reliably than enums.
+    #[wasm_bindgen]
+    pub fn get_matrix_codes(&self) -> Vec<i32> {
+        self.matrix.iter().map(|&player| player as i32).collect()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_turn_count(&self) -> usize {
+        self.moves.len()
+    }
 
+    #[wasm_bindgen]
+    pub fn is_board_full(&self) -> bool {
+        self.matrix.iter().all(|player| player != &Player::EMPTY)
+    }
+
+    /// One-line dump for logs, e.g.
+    /// `turn=X status=INPROGRESS winner=EMPTY moves=[4,0,8]`.
+    #[wasm_bindgen]
+    pub fn debug_state(&self) -> String {
+        let moves: Vec<String> = self.moves.iter().map(|mv| mv.to_string()).collect();
+        format!("turn={:?} status={:?} winner={:?} moves=[{}]",
+            self.turn, self.status, self.winner, moves.join(","))
+    }
+
+    /// Compares only what is on the board and who moves next, unlike `==`,
+    /// which also requires the same move order and settings.
+    #[wasm_bindgen]
+    pub fn same_position(&self, other: &Board) -> bool {
+        self.matrix == other.matrix
+            && self.turn == other.turn
+            && self.status == other.status
+            && self.winner == other.winner
+    }
+
+    #[wasm_bindgen]
+    pub fn get_empty_count(&self) -> usize {
+        self.matrix.iter().filter(|&player| player == &Player::EMPTY).count()
+    }
+
+    /// Piece tally as `[x_count, o_count, empty_count]`.
+    #[wasm_bindgen]
+    pub fn count_pieces(&self) -> Vec<usize> {
+        let count = |player: Player| self.matrix.iter().filter(|&&cell| cell == player).count();
+        vec![count(Player::X), count(Player::O), count(Player::EMPTY)]
+    }
+
+    #[wasm_bindgen]
+    pub fn get_move_history(&self) -> Vec<usize> {
+        self.moves.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_last_move(&self) -> Option<usize> {
+        self.moves.last().cloned()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_winning_line(&self) -> Option<Vec<usize>> {
+        if self.status != State::RESULTED {
+            return None;
+        }
+        self.find_completed_line()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_available_moves(&self) -> Vec<usize> {
+        find_available_moves(self)
+    }
+
+    #[wasm_bindgen]
+    pub fn render(&self) -> String {
+        self.matrix.chunks(self.size)
+            .map(|row| row.iter().map(|cell| match cell {
+                Player::X => 'X',
+                Player::O => 'O',
+                Player::EMPTY => '.',
+            }).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// A copy of the board turned a quarter turn clockwise.
+    #[wasm_bindgen]
+    pub fn rotate_90(&self) -> Board {
+        self.transformed(1)
+    }
+
+    /// A copy of the board flipped left to right.
+    #[wasm_bindgen]
+    pub fn mirror_horizontal(&self) -> Board {
+        self.transformed(4)
+    }
+
+    /// Base-3 packing of the matrix, taken in whichever of the eight
+    /// rotations/reflections packs smallest, so symmetric positions share a
+    /// key. Keys are unique for boards of up to 20 cells (4x4).
+    #[wasm_bindgen]
+    pub fn canonical_key(&self) -> u32 {
+        canonical_form(&self.matrix, self.size).iter()
+            .fold(0u32, |key, &cell| key.wrapping_mul(3).wrapping_add(cell as u32))
+    }
+
+    fn transformed(&self, symmetry: usize) -> Board {
+        let mut board = self.clone();
+        let map = |position: usize| symmetric_cell(self.size, symmetry, position);
+        for (position, &player) in self.matrix.iter().enumerate() {
+            board.matrix[map(position)] = player;
+        }
+        board.moves = self.moves.iter().map(|&position| map(position)).collect();
+        board.redo_stack = self.redo_stack.iter().map(|&position| map(position)).collect();
+        board.sync_bitboard();
+        board.recompute_status();
+        board
+    }
+
+    #[wasm_bindgen]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    #[wasm_bindgen]
+    pub fn from_json(json: &str) -> Result<Board, JsValue> {
+        let mut board: Board = serde_json::from_str(json)
+            .map_err(|err| JsValue::from(format!("Invalid Board JSON: {}", err)))?;
+        if let Some(message) = board.settings_error() {
+            return Err(JsValue::from(message));
+        }
+        board.lines = winning_lines(board.size, board.win_length);
+        if let Some(message) = board.history_error() {
+            return Err(JsValue::from(message));
+        }
+        board.sync_bitboard();
+        board.validate()?;
+        Ok(board)
+    }
+
+    /// Replays `moves` on a fresh board, failing with the `MoveError` of the
+    /// first move that make_move would reject.
+    #[wasm_bindgen]
+    pub fn from_moves(moves: Vec<usize>, start_player: Player, difficulty: Difficulty)
+        -> Result<Board, MoveError> {
+        let mut board = Board::new(start_player, difficulty);
+        for move_position in moves {
+            board.make_move(move_position)?;
+        }
+        Ok(board)
+    }
+
+    /// A practice position: `moves_count` random legal moves from an empty
+    /// board with X to start, stopping early if the game ends. The same seed
+    /// always gives the same position.
+    #[wasm_bindgen]
+    pub fn random_position(moves_count: usize, difficulty: Difficulty, seed: u64)
+        -> Result<Board, JsValue> {
+        if moves_count > 9 {
+            return Err(JsValue::from("Move Count Out Of Range."));
+        }
+
+        let mut board = Board::new_seeded(Player::X, difficulty, seed);
+        for _ in 0..moves_count {
+            if board.is_game_over() {
+                break;
+            }
+            let available_moves = board.get_available_moves();
+            let move_index = board.rng.0.gen_range(0, available_moves.len());
+            board.make_move(available_moves[move_index])?;
+        }
+        Ok(board)
+    }
+
+    /// Packs a 3x3 board into 15 bytes: the nine cells row by row, then the
+    /// turn, status, winner, difficulty, variant and early draw flag. The
+    /// move order is not kept, and other board sizes cannot be packed.
+    #[wasm_bindgen]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
+        if self.size != 3 || self.win_length != 3 {
+            return Err(JsValue::from("Only 3x3 Boards Can Be Packed Into Bytes."));
+        }
+
+        let mut bytes: Vec<u8> = self.matrix.iter().map(|&cell| cell.as_u8()).collect();
+        bytes.push(self.turn.as_u8());
+        bytes.push(self.status as u8);
+        bytes.push(self.winner.as_u8());
+        bytes.push(self.difficulty.as_u8());
+        bytes.push(self.variant as u8);
+        bytes.push(self.early_draw as u8);
+        Ok(bytes)
+    }
+
+    #[wasm_bindgen]
+    pub fn from_bytes(data: &[u8]) -> Result<Board, JsValue> {
+        if data.len() != 15 {
+            return Err(JsValue::from("Board Bytes Must Have Length 15."));
+        }
+
+        let player_at = |index: usize| Player::from_u8(data[index])
+            .ok_or_else(|| JsValue::from("Invalid Player Byte."));
+        let mut cells = vec![];
+        for index in 0..9 {
+            cells.push(player_at(index)?);
+        }
+        let turn = player_at(9)?;
+        let status = state_from_u8(data[10]).ok_or_else(|| JsValue::from("Invalid Status Byte."))?;
+        let winner = player_at(11)?;
+        let difficulty = Difficulty::from_u8(data[12])
+            .ok_or_else(|| JsValue::from("Invalid Difficulty Byte."))?;
+        let variant = variant_from_u8(data[13])
+            .ok_or_else(|| JsValue::from("Invalid Variant Byte."))?;
+        let early_draw = match data[14] {
+            0 => false,
+            1 => true,
+            _ => return Err(JsValue::from("Invalid Early Draw Byte.")),
+        };
+
+        let mut board = Board::from_matrix_with_rules(cells, difficulty, variant, early_draw)?;
+        // from_matrix has X open whenever the counts are level; O opening
+        // instead only swaps each X/O pair of the reconstructed history.
+        if turn == Player::O && board.moves.len() % 2 == 0 {
+            for pair in board.moves.chunks_mut(2) {
+                pair.swap(0, 1);
+            }
+            board.start_player = Player::O;
+            board.turn = Player::O;
+        }
+        if board.turn != turn {
+            return Err(JsValue::from("Turn Does Not Match Board."));
+        }
+        if board.status != status || board.winner != winner {
+            return Err(JsValue::from("Status Does Not Match Board."));
+        }
+        Ok(board)
+    }
+
+    /// Checks that the status and winner agree with the pieces: a resulted
+    /// game has a winner and the completed line that made them one, a drawn
+    /// game has no winner and no move that could still change that, and a
+    /// game in progress has no completed line and a move left to play. In
+    /// every case the status and winner must be the ones the pieces call for.
+    #[wasm_bindgen]
+    pub fn validate(&self) -> Result<(), JsValue> {
+        match self.status_error() {
+            Some(message) => Err(JsValue::from(message)),
+            None => Ok(()),
+        }
+    }
+
+    // The first of validate's checks that fails, as its message.
+    fn status_error(&self) -> Option<&'static str> {
+        let line_owner = self.find_completed_line().map(|line| self.matrix[line[0]]);
+        match self.status {
+            State::RESULTED => {
+                if self.winner == Player::EMPTY {
+                    return Some("Resulted Board Has No Winner.");
+                }
+                if line_owner.map(|owner| self.line_winner(owner)) != Some(self.winner) {
+                    return Some("Winner Does Not Match Completed Line.");
+                }
+            }
+            State::DRAW => {
+                if self.winner != Player::EMPTY {
+                    return Some("Drawn Board Has A Winner.");
+                }
+                if line_owner.is_some() {
+                    return Some("Drawn Board Has A Completed Line.");
+                }
+                if !self.is_board_full() && !self.is_dead_draw() {
+                    return Some("Drawn Board Can Still Be Won.");
+                }
+            }
+            State::INPROGRESS => {
+                if self.winner != Player::EMPTY {
+                    return Some("Board In Progress Has A Winner.");
+                }
+                if line_owner.is_some() {
+                    return Some("Board In Progress Has A Completed Line.");
+                }
+                if self.is_drawn() {
+                    return Some("Board In Progress Is Already Drawn.");
+                }
+            }
+        }
+        if (self.status, self.winner) != self.computed_status() {
+            return Some("Status Does Not Match Board.");
+        }
+        None
+    }
+
+    // The first setting outside the range the constructors and setters keep
+    // it in, as its message. Loaded JSON bypasses both.
+    fn settings_error(&self) -> Option<&'static str> {
+        if self.size == 0 {
+            return Some("Board Size Must Be At Least 1.");
+        }
+        // Checked before anything is sized from `size`, which may be huge.
+        if self.size.checked_mul(self.size) != Some(self.matrix.len()) {
+            return Some("Board Size Does Not Match Matrix.");
+        }
+        if self.win_length == 0 || self.win_length > self.size {
+            return Some("Win Length Must Be Between 1 And The Board Size.");
+        }
+        if self.mistake_rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate.0)) {
+            return Some("Mistake Rate Must Be Between 0 And 1.");
+        }
+        if !self.search_scores.is_ordered() {
+            return Some("Search Scores Must Order Win Above Draw Above Loss.");
+        }
+        None
+    }
+
+    // The first way the moves list fails to explain the matrix, as its
+    // message, once settings_error has passed: every move has to be a
+    // distinct in-range cell, players alternate from start_player ending with
+    // whoever is not on turn, and no other cell is filled. The redo stack has
+    // to hold distinct empty cells.
+    fn history_error(&self) -> Option<&'static str> {
+        if self.turn == Player::EMPTY || self.start_player == Player::EMPTY {
+            return Some("Players Cannot Be Empty.");
+        }
+        let expected_turn = if self.moves.len().is_multiple_of(2) {
+            self.start_player
+        } else {
+            opponent(self.start_player)
+        };
+        if self.turn != expected_turn {
+            return Some("Turn Does Not Match Starting Player.");
+        }
+
+        let mut filled = vec![false; self.matrix.len()];
+        let mut player = self.turn;
+        for &move_position in self.moves.iter().rev() {
+            player = opponent(player);
+            if move_position >= self.matrix.len() || filled[move_position] {
+                return Some("Move History Is Invalid.");
+            }
+            if self.matrix[move_position] != player {
+                return Some("Move History Does Not Match Matrix.");
+            }
+            filled[move_position] = true;
+        }
+        if self.move_times.0.stamps.len() != self.moves.len() {
+            return Some("Move Timestamps Do Not Match History.");
+        }
+
+        let stray_piece = self.matrix.iter().enumerate()
+            .any(|(index, cell)| !filled[index] && cell != &Player::EMPTY);
+        if stray_piece {
+            return Some("Move History Does Not Match Matrix.");
+        }
+
+        for &move_position in self.redo_stack.iter() {
+            if move_position >= self.matrix.len() || filled[move_position] {
+                return Some("Redo Stack Is Invalid.");
+            }
+            filled[move_position] = true;
+        }
+
+        None
+    }
+}
+
+impl Board {
+    pub fn evaluate_moves(&mut self) -> Vec<(usize, i32)> {
+        self.search_moves(self.max_depth)
+    }
+
+    /// get_best_move with the progress reported to `progress` instead of
+    /// the JS callback. Answers that need no search report every move as
+    /// done in a single call.
+    pub fn get_best_move_with_progress(&mut self, progress: &mut dyn FnMut(usize, usize))
+        -> Option<usize> {
+        // Every corner and the center are optimal openings on the classic
+        // board, so there is no need to search the whole tree for them. That
+        // does not hold for misere, where only the center is safe.
+        if self.moves.is_empty() && self.size == 3 && self.win_length == 3
+            && self.variant == Variant::STANDARD {
+            self.search_stats.0.nodes = 0;
+            progress(self.matrix.len(), self.matrix.len());
+            let opening_moves: [usize; 5] = [0, 2, 4, 6, 8];
+            let move_index = self.rng.0.gen_range(0, opening_moves.len());
+            return Some(opening_moves[move_index]);
+        }
+
+        if let Some(mv) = self.cached_best_move() {
+            self.search_stats.0.nodes = 0;
+            let total = find_available_moves(self).len();
+            progress(total, total);
+            return Some(mv);
+        }
+
+        let scores = self.search_moves_reporting(self.max_depth, self.cancel.0.0.clone(), progress);
+        if !self.search_stats.0.stopped {
+            self.cache_best_moves(&best_scoring_moves(&scores));
+        }
+        self.pick_best_move(scores)
+    }
+
+    fn best_move_cache_entry(&self, canonical_moves: Vec<usize>) -> CachedMove {
+        CachedMove {
+            key: self.canonical_key(),
+            turn: self.turn,
+            size: self.size,
+            win_length: self.win_length,
+            max_depth: self.max_depth,
+            search_scores: self.search_scores,
+            variant: self.variant,
+            early_draw: self.early_draw,
+            canonical_moves,
+        }
+    }
+
+    // Canonical keys are only unique up to 20 cells, so larger boards are
+    // never cached. A hit still draws among the tied moves, so repeated
+    // games from the same position do not all get the same reply.
+    fn cached_best_move(&mut self) -> Option<usize> {
+        if self.matrix.len() > 20 {
+            return None;
+        }
+
+        let cached = self.best_move_cache.0.borrow().clone()?;
+        if cached != self.best_move_cache_entry(cached.canonical_moves.clone()) {
+            return None;
+        }
+        let symmetry = canonical_symmetry(&self.matrix, self.size);
+        let mut best_moves: Vec<usize> = cached.canonical_moves.iter()
+            .map(|&mv| symmetric_cell(self.size, symmetry, mv))
+            .collect();
+        best_moves.sort_unstable();
+        let move_index = self.rng.0.gen_range(0, best_moves.len());
+        Some(best_moves[move_index])
+    }
+
+    fn cache_best_moves(&self, best_moves: &[usize]) {
+        if self.matrix.len() > 20 || best_moves.is_empty() {
+            return;
+        }
+
+        let symmetry = canonical_symmetry(&self.matrix, self.size);
+        let canonical_moves = (0..self.matrix.len())
+            .filter(|&index| best_moves.contains(&symmetric_cell(self.size, symmetry, index)))
+            .collect();
+        *self.best_move_cache.0.borrow_mut() = Some(self.best_move_cache_entry(canonical_moves));
+    }
+
+    // Analysis searches get a flag of their own that nothing sets, so a
+    // stop() meant for the AI can neither cut them short nor be used up.
+    fn search_moves(&mut self, max_depth: Option<usize>) -> Vec<(usize, i32)> {
+        self.search_moves_reporting(max_depth, Arc::default(), &mut |_, _| {})
+    }
+
+    // Scores every root move, calling `progress(done, total)` as each one is
+    // settled and returning early once `cancel` is set. Root moves that are
+    // mirror images of one already searched lead to the same value, so they
+    // reuse its score instead of a new search.
+    fn search_moves_reporting(&mut self, max_depth: Option<usize>, cancel: Arc<AtomicBool>,
+        progress: &mut dyn FnMut(usize, usize)) -> Vec<(usize, i32)> {
+        self.search_stats.0.nodes = 0;
+        let mut scores: Vec<(usize, i32)> = vec![];
+        if self.status != State::INPROGRESS {
+            self.search_stats.0.stopped = cancel.swap(false, Ordering::Relaxed);
+            return scores;
+        }
+
+        let root_moves = find_available_moves(self);
+        let mut full_search = Search::new(None, cancel.clone());
+        let mut searched: HashMap<Vec<u8>, i32> = HashMap::new();
+        for (index, &mv) in root_moves.iter().enumerate() {
+            self.apply_move(mv);
+            let key = canonical_form(&self.matrix, self.size);
+            let score = match searched.get(&key) {
+                Some(&score) => score,
+                None => {
+                    let score = match max_depth {
+                        None => {
+                            let mover = self.turn;
+                            minimax(self, &mover, 0, i32::MIN, i32::MAX, &mut full_search)
+                        }
+                        Some(limit) => self.deepen(limit, &cancel),
+                    };
+                    searched.insert(key, score);
+                    score
+                }
+            };
+            self.revert_move();
+            scores.push((mv, score));
+            progress(index + 1, root_moves.len());
+        }
+
+        self.search_stats.0.nodes += full_search.nodes;
+        self.search_stats.0.stopped = cancel.swap(false, Ordering::Relaxed);
+        scores
+    }
+
+    // Iterative deepening below one root move: search one ply deeper each
+    // round and stop once a round finishes without hitting the depth limit.
+    // A stopped search keeps the last round that finished, if there was one.
+    fn deepen(&mut self, limit: usize, cancel: &Arc<AtomicBool>) -> i32 {
+        let mover = self.turn;
+        let mut score = 0;
+        for depth in 1..=limit.max(1) {
+            let mut search = Search::new(Some(depth), cancel.clone());
+            let depth_score = minimax(self, &mover, 0, i32::MIN, i32::MAX, &mut search);
+            self.search_stats.0.nodes += search.nodes;
+            if cancel.load(Ordering::Relaxed) {
+                if depth == 1 {
+                    score = depth_score;
+                }
+                break;
+            }
+            score = depth_score;
+            if !search.cut_off {
+                break;
+            }
+        }
+        score
+    }
+
+    /// The flag behind stop(), for stopping a search from another thread.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.cancel.0.0.clone()
+    }
+
+    /// Replaces the wall clock used to timestamp moves, e.g. with a fake one
+    /// so replays can be tested without depending on real time.
+    pub fn set_clock(&mut self, clock: fn() -> f64) {
+        self.move_times.0.clock = Some(clock);
+    }
+
+    /// Static evaluation from `mover`'s point of view: near wins held by
+    /// `mover` minus those held by the opponent, or +/-100 once the game has
+    /// been won.
+    pub fn heuristic_score(&self, mover: &Player) -> i32 {
+        if self.status == State::RESULTED {
+            return if &self.winner == mover { 100 } else { -100 };
+        }
+
+        let near_wins = count_near_wins(self, *mover) - count_near_wins(self, opponent(*mover));
+        match self.variant {
+            Variant::STANDARD => near_wins,
+            Variant::MISERE => -near_wins,
+        }
+    }
+
+    pub fn from_matrix(cells: Vec<Player>, difficulty: Difficulty) -> Result<Board, JsValue> {
+        Board::from_matrix_with_rules(cells, difficulty, Variant::STANDARD, false)
+    }
+
+    // from_matrix, with the status worked out under `variant` and
+    // `early_draw` instead of the standard rules.
+    fn from_matrix_with_rules(cells: Vec<Player>, difficulty: Difficulty, variant: Variant,
+        early_draw: bool) -> Result<Board, JsValue> {
+        if cells.len() != 9 {
+            return Err(JsValue::from("Board Must Have Exactly 9 Cells."));
+        }
+
+        let cells_of = |player: Player| -> Vec<usize> {
+            cells.iter().enumerate()
+                .filter(|&(_, cell)| cell == &player)
+                .map(|(index, _)| index)
+                .collect()
+        };
+        let x_cells = cells_of(Player::X);
+        let o_cells = cells_of(Player::O);
+
+        // With equal counts we assume X opened, so X is on turn again.
+        let (start_player, first, second) = if x_cells.len() == o_cells.len() + 1 {
+            (Player::X, x_cells, o_cells)
+        } else if o_cells.len() == x_cells.len() + 1 {
+            (Player::O, o_cells, x_cells)
+        } else if x_cells.len() == o_cells.len() {
+            (Player::X, x_cells, o_cells)
+        } else {
+            return Err(JsValue::from("Impossible Piece Count Difference."));
+        };
+
+        let mut board = Board::new(start_player, difficulty);
+        board.variant = variant;
+        board.early_draw = early_draw;
+        for index in 0..first.len() {
+            board.moves.push(first[index]);
+            if index < second.len() {
+                board.moves.push(second[index]);
+            }
+        }
+        if board.moves.len() % 2 == 1 {
+            board.change_turn();
+        }
+        board.move_times.0.stamps = vec![0.0; board.moves.len()];
+        board.matrix = cells;
+        board.sync_bitboard();
+
+        let has_line = |player: &Player| board.lines.iter()
+            .any(|line| line.iter().all(|&cell| &board.matrix[cell] == player));
+        if has_line(&Player::X) && has_line(&Player::O) {
+            return Err(JsValue::from("Both Players Cannot Have Won."));
+        }
+
+        board.recompute_status();
+        board.validate()?;
+        Ok(board)
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(Player::X, Difficulty::DIFFICULT)
+    }
+}
+
+#[wasm_bindgen]
+pub fn play_out(start_player: Player, x_difficulty: Difficulty, o_difficulty: Difficulty,
+    seed: u64) -> State {
+    play_out_board(start_player, x_difficulty, o_difficulty, seed).get_board_state()
+}
+
+/// The eight winning lines of the classic 3x3 board as cell indices. Boards
+/// of other sizes check the equivalent lines for their own size internally.
+pub fn get_lines() -> Vec<Vec<usize>> {
+    winning_lines(3, 3)
+}
+
+/// The moves of the game play_out would play with the same arguments, in
+/// order, so a seed pins down a reproducible fixture.
+#[wasm_bindgen]
+pub fn play_out_transcript(start_player: Player, x_difficulty: Difficulty,
+    o_difficulty: Difficulty, seed: u64) -> Vec<usize> {
+    play_out_board(start_player, x_difficulty, o_difficulty, seed).get_move_history()
+}
+
+/// Plays `games` seeded AI-vs-AI games, alternating who starts, and returns
+/// the tally as `[x_wins, o_wins, draws]`.
+#[wasm_bindgen]
+pub fn simulate_many(games: u32, x_difficulty: Difficulty, o_difficulty: Difficulty,
+    seed: u64) -> Vec<u32> {
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let mut counts: Vec<u32> = vec![0, 0, 0];
+    for game in 0..games {
+        let start_player = if game % 2 == 0 { Player::X } else { Player::O };
+        let board = play_out_board(start_player, x_difficulty.clone(), o_difficulty.clone(),
+            seeds.gen::<u64>());
+        match board.get_winner() {
+            Player::X => counts[0] += 1,
+            Player::O => counts[1] += 1,
+            Player::EMPTY => counts[2] += 1,
+        }
+    }
+
+    counts
+}
+
+fn play_out_board(start_player: Player, x_difficulty: Difficulty, o_difficulty: Difficulty,
+    seed: u64) -> Board {
+    let mut board = Board::new_seeded(start_player, x_difficulty.clone(), seed);
+    while !board.is_game_over() {
+        let difficulty = match board.turn {
+            Player::O => o_difficulty.clone(),
+            _ => x_difficulty.clone(),
+        };
+        board.set_difficulty(difficulty);
+        match board.get_next_move() {
+            Some(mv) => board.make_move(mv).unwrap(),
+            None => break,
+        }
+    }
+
+    board
+}
+
+// Per-search state shared by every minimax call of one root search.
+struct Search {
+    cache: HashMap<Vec<Player>, i32>,
+    max_depth: Option<usize>,
+    cut_off: bool,
+    nodes: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Search {
+    fn new(max_depth: Option<usize>, cancel: Arc<AtomicBool>) -> Self {
+        Search {
+            cache: HashMap::new(),
+            max_depth,
+            cut_off: false,
+            nodes: 0,
+            cancel,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn system_time_millis() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn system_time_millis() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs() as f64 * 1000.0
+            + f64::from(elapsed.subsec_millis()))
+}
+
+fn entropy_rng() -> Ignored<StdRng> {
+    Ignored(StdRng::from_entropy())
+}
+
+fn starting_player(player: Player) -> Player {
+    if player == Player::EMPTY { Player::X } else { player }
+}
+
+fn state_from_u8(value: u8) -> Option<State> {
+    match value {
+        0 => Some(State::DRAW),
+        1 => Some(State::RESULTED),
+        2 => Some(State::INPROGRESS),
+        _ => None,
+    }
+}
+
+fn variant_from_u8(value: u8) -> Option<Variant> {
+    match value {
+        0 => Some(Variant::STANDARD),
+        1 => Some(Variant::MISERE),
+        _ => None,
+    }
+}
+
+fn opponent(player: Player) -> Player {
+    match player {
+        Player::X => Player::O,
+        Player::O => Player::X,
+        Player::EMPTY => Player::EMPTY,
+    }
+}
+
+// Maps `index` through one of the eight rotations/reflections of a square
+// board: `symmetry % 4` quarter turns, mirrored when `symmetry >= 4`.
+fn symmetric_cell(size: usize, symmetry: usize, index: usize) -> usize {
+    let (mut row, mut col) = (index / size, index % size);
+    for _ in 0..symmetry % 4 {
+        let turned = (col, size - 1 - row);
+        row = turned.0;
+        col = turned.1;
+    }
+    if symmetry >= 4 {
+        col = size - 1 - col;
+    }
+    row * size + col
+}
+
+// The smallest encoding of `matrix` over all eight symmetries, equal for any
+// two positions that are rotations or reflections of each other.
+fn canonical_form(matrix: &[Player], size: usize) -> Vec<u8> {
+    symmetric_form(matrix, size, canonical_symmetry(matrix, size))
+}
+
+// The symmetry canonical_form reads `matrix` through: cell `index` of the
+// canonical form is cell `symmetric_cell(size, symmetry, index)` of `matrix`.
+fn canonical_symmetry(matrix: &[Player], size: usize) -> usize {
+    (0..8).min_by_key(|&symmetry| symmetric_form(matrix, size, symmetry)).unwrap()
+}
+
+fn symmetric_form(matrix: &[Player], size: usize, symmetry: usize) -> Vec<u8> {
+    (0..matrix.len())
+        .map(|index| matrix[symmetric_cell(size, symmetry, index)] as u8)
+        .collect()
+}
+
+fn winning_lines(size: usize, win_length: usize) -> Vec<Vec<usize>> {
+    let mut lines: Vec<Vec<usize>> = vec![];
+    if win_length == 0 || win_length > size {
+        return lines;
+    }
+
+    let directions: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+    for &(d_row, d_col) in directions.iter() {
+        for row in 0..size as isize {
+            for col in 0..size as isize {
+                let end_row = row + d_row * (win_length as isize - 1);
+                let end_col = col + d_col * (win_length as isize - 1);
+                if end_row >= size as isize || end_col < 0 || end_col >= size as isize {
+                    continue;
+                }
+                lines.push((0..win_length as isize)
+                    .map(|i| ((row + d_row * i) * size as isize + col + d_col * i) as usize)
+                    .collect());
+            }
+        }
+    }
+
+    lines
+}
+
+fn best_scoring_moves(scores: &[(usize, i32)]) -> Vec<usize> {
+    let best_score = match scores.iter().map(|&(_, score)| score).max() {
+        Some(score) => score,
+        None => return vec![],
+    };
+
+    scores.iter()
+        .filter(|&&(_, score)| score == best_score)
+        .map(|&(mv, _)| mv)
+        .collect()
+}
+
+fn find_available_moves(board: &Board) -> Vec<usize> {
+    let mut available_moves: Vec<usize> = vec![];
+
+    for (index, player) in board.matrix.iter().enumerate() {
+        if player == &Player::EMPTY {
+            available_moves.push(index);
+        }
+    }
+
+    available_moves
+}
+
+// Lines where `player` is a single piece away from completing the line.
+fn count_near_wins(board: &Board, player: Player) -> i32 {
+    board.lines.iter()
+        .filter(|line| {
+            let pieces = line.iter().filter(|&&cell| board.matrix[cell] == player).count();
+            let empties = line.iter().filter(|&&cell| board.matrix[cell] == Player::EMPTY).count();
+            pieces + 1 == line.len() && empties == 1
+        })
+        .count() as i32
+}
+
+// Scores a position where a depth-limited search stopped, on the same scale
+// as minimax. Whoever is on turn with a near win takes it next move;
+// otherwise compare how many near wins each side holds.
+fn evaluate_leaf(board: &Board, mover: &Player, depth: i32) -> i32 {
+    let searcher = opponent(*mover);
+    if board.variant == Variant::STANDARD && count_near_wins(board, board.turn) > 0 {
+        let scores = board.search_scores;
+        return if board.turn == searcher {
+            scores.won_at(depth + 1)
+        } else {
+            scores.lost_at(depth + 1)
+        };
+    }
+
+    board.heuristic_score(&searcher)
+}
+
+fn minimax(board: &mut Board, mover: &Player, depth: i32, mut alpha: i32, mut beta: i32,
+    search: &mut Search) -> i32 {
+    search.nodes += 1;
+    if board.status == State::RESULTED {
+        let scores = board.search_scores;
+        return if &board.winner != mover { scores.won_at(depth) } else { scores.lost_at(depth) };
+    } else if board.status == State::DRAW { return board.search_scores.draw; }
+
+    if search.cancel.load(Ordering::Relaxed) {
+        search.cut_off = true;
+        return evaluate_leaf(board, mover, depth);
+    }
+
+    if let Some(max_depth) = search.max_depth {
+        if depth as usize + 1 >= max_depth {
+            search.cut_off = true;
+            return evaluate_leaf(board, mover, depth);
+        }
+    }
+
+    if let Some(&score) = search.cache.get(&board.matrix) {
+        return score;
+    }
+
+    // Only scores strictly inside the original window are exact; anything
+    // else was cut off by pruning and is just a bound.
+    let (alpha_orig, beta_orig) = (alpha, beta);
+    let is_max = &board.turn != mover;
+    // set_search_scores allows any i32, so start from the extremes.
+    let mut best_score = if is_max { i32::MIN } else { i32::MAX };
+    for mv in find_available_moves(board) {
+        board.apply_move(mv);
+        let score = minimax(board, mover, depth + 1, alpha, beta, search);
+        board.revert_move();
+        if is_max && score > best_score {
+            best_score = score;
+            alpha = alpha.max(best_score);
+        }
+        if !is_max && score < best_score {
+            best_score = score;
+            beta = beta.min(best_score);
+        }
+        if beta <= alpha {
+            break;
+        }
+    }
+
+    if alpha_orig < best_score && best_score < beta_orig {
+        search.cache.insert(board.matrix.clone(), best_score);
+    }
+
+    best_score
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::HashSet;
+
+    // X opens and nobody completes a line: XOX / XOO / OXX.
+    const DRAWN_GAME: [usize; 9] = [0, 4, 8, 1, 7, 6, 2, 5, 3];
+
+    fn play(board: &mut Board, moves: &[usize]) {
+        for &move_position in moves {
+            board.make_move(move_position).unwrap();
+        }
+    }
+
+    #[test]
+    fn make_move_after_a_win_is_rejected() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        let matrix = board.matrix.clone();
+        let moves = board.moves.clone();
+
+        assert_eq!(board.make_move(5), Err(MoveError::GameOver));
+        assert_eq!(board.matrix, matrix);
+        assert_eq!(board.moves, moves);
+        assert_eq!(board.get_winner(), Player::X);
+    }
+
+    #[test]
+    fn search_still_finishes_on_boards_in_progress() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[4]);
+        let best_move = board.get_best_move().unwrap();
+        assert!(board.is_legal_move(best_move));
+        assert_eq!(board.get_move_history(), vec![4]);
+    }
+
+    #[test]
+    fn undoing_a_won_game_back_to_empty_clears_the_result() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        for _ in 0..5 {
+            board.undo_move().unwrap();
+        }
+
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        assert_eq!(board.get_winner(), Player::EMPTY);
+    }
+
+    #[test]
+    fn undoing_the_winning_move_reopens_the_game() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+
+        assert_eq!(board.undo_move().unwrap(), 2);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        assert_eq!(board.get_winner(), Player::EMPTY);
+        assert_eq!(board.get_current_turn(), Player::X);
+    }
+
+    #[test]
+    fn next_move_on_a_drawn_board_is_none() {
+        for difficulty in [Difficulty::EASY, Difficulty::MEDIUM, Difficulty::DIFFICULT,
+            Difficulty::IMPOSSIBLE] {
+            let mut board = Board::new(Player::X, difficulty);
+            play(&mut board, &DRAWN_GAME);
+            assert_eq!(board.get_board_state(), State::DRAW);
+            assert_eq!(board.get_next_move(), None);
+            assert_eq!(board.get_random_move(), None);
+        }
+    }
+
+    #[test]
+    fn available_moves_shrink_as_the_board_fills() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.get_available_moves(), (0..=8).collect::<Vec<usize>>());
+
+        play(&mut board, &[4, 0]);
+        assert_eq!(board.get_available_moves(), vec![1, 2, 3, 5, 6, 7, 8]);
+
+        play(&mut board, &[8, 1, 7, 6, 2, 5, 3]);
+        assert!(board.get_available_moves().is_empty());
+    }
+
+    #[test]
+    fn matrix_reports_pieces_row_major() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 2]);
+        use Player::{EMPTY as E, O, X};
+        assert_eq!(board.get_matrix(), vec![E, E, O, E, X, E, E, E, E]);
+    }
+
+    // Plain minimax over the whole tree, kept to check the pruned search.
+    fn full_minimax(board: &mut Board, mover: Player, depth: i32, nodes: &mut u64) -> i32 {
+        *nodes += 1;
+        let scores = board.search_scores;
+        match board.status {
+            State::RESULTED if board.winner != mover => return scores.won_at(depth),
+            State::RESULTED => return scores.lost_at(depth),
+            State::DRAW => return scores.draw,
+            State::INPROGRESS => {}
+        }
+
+        let mut results: Vec<i32> = vec![];
+        for mv in find_available_moves(board) {
+            board.apply_move(mv);
+            results.push(full_minimax(board, mover, depth + 1, nodes));
+            board.revert_move();
+        }
+        if board.turn != mover {
+            results.into_iter().max().unwrap()
+        } else {
+            results.into_iter().min().unwrap()
+        }
+    }
+
+    #[test]
+    fn pruned_minimax_matches_full_minimax_with_fewer_nodes() {
+        for moves in [vec![4], vec![0], vec![4, 0], vec![0, 4, 8], vec![0, 1, 3, 4]].iter() {
+            let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+            play(&mut board, moves);
+            let mover = board.turn;
+            let mut full_nodes = 0;
+            let expected = full_minimax(&mut board, mover, 0, &mut full_nodes);
+
+            let mut search = Search::new(None, Arc::new(AtomicBool::new(false)));
+            let score = minimax(&mut board, &mover, 0, i32::MIN, i32::MAX, &mut search);
+            assert_eq!(score, expected, "after {:?}", moves);
+            assert!(search.nodes < full_nodes, "after {:?}", moves);
+            assert_eq!(&board.moves, moves);
+        }
+    }
+
+    #[test]
+    fn best_move_takes_the_immediate_win() {
+        // X can win at once on 2, or later through the fork on 6.
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 4, 1, 8]);
+        let scores = board.evaluate_moves();
+        let score_of = |mv: usize| scores.iter().find(|&&(cell, _)| cell == mv).unwrap().1;
+        assert!(score_of(2) > score_of(6));
+        assert!(score_of(6) > 0);
+
+        assert_eq!(board.get_best_move(), Some(2));
+    }
+
+    #[test]
+    fn transposition_table_keeps_scores_and_cuts_nodes() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0]);
+        let mover = board.turn;
+        let mut full_nodes = 0;
+        let expected = full_minimax(&mut board, mover, 0, &mut full_nodes);
+
+        let mut search = Search::new(None, Arc::new(AtomicBool::new(false)));
+        assert_eq!(minimax(&mut board, &mover, 0, i32::MIN, i32::MAX, &mut search), expected);
+        assert!(!search.cache.is_empty());
+        assert!(search.nodes * 10 < full_nodes);
+    }
+
+    #[test]
+    fn best_move_varies_among_tied_moves() {
+        let mut board = Board::new_seeded(Player::X, Difficulty::DIFFICULT, 7);
+        let openings: HashSet<usize> = (0..50).map(|_| board.get_best_move().unwrap()).collect();
+        assert!(openings.len() > 1);
+
+        // Only the corners hold the draw against a center opening.
+        play(&mut board, &[4]);
+        let replies: HashSet<usize> = (0..50).map(|_| board.get_best_move().unwrap()).collect();
+        assert!(replies.len() > 1);
+        assert!(replies.iter().all(|mv| [0, 2, 6, 8].contains(mv)));
+    }
+
+    #[test]
+    fn four_by_four_board_detects_a_full_row() {
+        let mut board = Board::new_sized(Player::X, Difficulty::EASY, 4);
+        play(&mut board, &[4, 0, 5, 1, 6, 2]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        play(&mut board, &[7]);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::X);
+    }
+
+    #[test]
+    fn four_by_four_board_detects_a_full_diagonal() {
+        let mut board = Board::new_sized(Player::X, Difficulty::EASY, 4);
+        play(&mut board, &[3, 0, 6, 1, 9, 2]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        play(&mut board, &[12]);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::X);
+    }
+
+    #[test]
+    fn empty_board_size_is_read_as_one() {
+        let mut board = Board::new_sized(Player::X, Difficulty::EASY, 0);
+        assert_eq!(board.get_available_moves(), vec![0]);
+        play(&mut board, &[0]);
+        assert_eq!(board.get_winner(), Player::X);
+    }
+
+    #[test]
+    fn four_in_a_row_wins_on_a_five_by_five_board() {
+        let mut board = Board::new_with_win_length(Player::X, Difficulty::EASY, 5, 4);
+        play(&mut board, &[6, 0, 7, 1, 8, 2]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        play(&mut board, &[9]);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::X);
+    }
+
+    #[test]
+    fn win_length_is_clamped_to_the_board() {
+        let board = Board::new_with_win_length(Player::X, Difficulty::EASY, 3, 0);
+        assert_eq!(board.win_length, 1);
+        let board = Board::new_with_win_length(Player::X, Difficulty::EASY, 3, 5);
+        assert_eq!(board.win_length, 3);
+        let board = Board::try_new_with_win_length(Player::X, Difficulty::EASY, 5, 4).unwrap();
+        assert_eq!(board.win_length, 4);
+    }
+
+    #[test]
+    fn winning_line_lists_the_completed_cells() {
+        let cases: [(&[usize], [usize; 3]); 3] = [
+            (&[3, 0, 4, 1, 5], [3, 4, 5]),
+            (&[1, 0, 4, 2, 7], [1, 4, 7]),
+            (&[2, 0, 4, 1, 6], [2, 4, 6]),
+        ];
+        for &(moves, line) in cases.iter() {
+            let mut board = Board::new(Player::X, Difficulty::EASY);
+            play(&mut board, moves);
+            assert_eq!(board.get_winning_line(), Some(line.to_vec()));
+        }
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 4]);
+        assert_eq!(board.get_winning_line(), None);
+    }
+
+    #[test]
+    fn undo_move_restores_the_previous_position() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4]);
+        let matrix = board.matrix.clone();
+        play(&mut board, &[0]);
+
+        assert_eq!(board.undo_move().unwrap(), 0);
+        assert_eq!(board.get_current_turn(), Player::O);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        assert_eq!(board.get_winner(), Player::EMPTY);
+        assert_eq!(board.matrix, matrix);
+    }
+
+    #[test]
+    fn nothing_to_undo_on_a_fresh_board() {
+        // undo_move's error is a JsValue, so check the revert it is built on.
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.revert_move(), None);
+        assert_eq!(board, Board::new(Player::X, Difficulty::EASY));
+    }
+
+    #[test]
+    fn reset_clears_the_game_but_keeps_the_difficulty() {
+        let mut board = Board::new(Player::X, Difficulty::MEDIUM);
+        play(&mut board, &DRAWN_GAME);
+        board.reset(Player::O);
+
+        assert_eq!(board, Board::new(Player::O, Difficulty::MEDIUM));
+        assert_eq!(board.get_current_turn(), Player::O);
+        assert_eq!(board.get_difficulty(), Difficulty::MEDIUM);
+    }
+
+    #[test]
+    fn json_round_trip_keeps_every_field() {
+        let mut board = Board::new(Player::O, Difficulty::MEDIUM);
+        play(&mut board, &[4, 0, 8]);
+
+        let restored = Board::from_json(&board.to_json()).unwrap();
+        assert_eq!(restored, board);
+
+        // The lines are not stored, so loading has to rebuild them.
+        let mut board = Board::new_with_win_length(Player::X, Difficulty::MEDIUM, 4, 3);
+        play(&mut board, &[0, 4, 1]);
+        let restored = Board::from_json(&board.to_json()).unwrap();
+        assert_eq!(restored.lines, winning_lines(4, 3));
+        assert_eq!(restored, board);
+    }
+
+    #[test]
+    fn settings_error_rejects_what_the_setters_never_allow() {
+        let loaded = |field: &str, value: &str| {
+            let mut json: serde_json::Value =
+                serde_json::from_str(&Board::default().to_json()).unwrap();
+            json[field] = serde_json::from_str(value).unwrap();
+            let board: Board = serde_json::from_value(json).unwrap();
+            board.settings_error()
+        };
+
+        assert_eq!(loaded("win_length", "3"), None);
+        assert_eq!(loaded("mistake_rate", "0.5"), None);
+        assert_eq!(loaded("size", "0"), Some("Board Size Must Be At Least 1."));
+        assert_eq!(loaded("size", "30000"), Some("Board Size Does Not Match Matrix."));
+        assert_eq!(loaded("size", &usize::MAX.to_string()),
+            Some("Board Size Does Not Match Matrix."));
+        assert_eq!(loaded("win_length", "0"),
+            Some("Win Length Must Be Between 1 And The Board Size."));
+        assert_eq!(loaded("win_length", "4"),
+            Some("Win Length Must Be Between 1 And The Board Size."));
+        assert_eq!(loaded("mistake_rate", "7.5"), Some("Mistake Rate Must Be Between 0 And 1."));
+        assert_eq!(loaded("mistake_rate", "-0.5"), Some("Mistake Rate Must Be Between 0 And 1."));
+    }
+
+    #[test]
+    fn history_error_rejects_moves_that_do_not_explain_the_matrix() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 0]);
+        assert_eq!(board.history_error(), None);
+
+        let mut swapped = board.clone();
+        swapped.moves = vec![0, 4];
+        assert_eq!(swapped.history_error(), Some("Move History Does Not Match Matrix."));
+
+        let mut repeated = board.clone();
+        repeated.moves = vec![0, 0];
+        assert_eq!(repeated.history_error(), Some("Move History Is Invalid."));
+
+        let mut stray = board.clone();
+        stray.matrix[8] = Player::X;
+        assert_eq!(stray.history_error(), Some("Move History Does Not Match Matrix."));
+
+        let mut wrong_turn = board.clone();
+        wrong_turn.turn = Player::O;
+        assert_eq!(wrong_turn.history_error(), Some("Turn Does Not Match Starting Player."));
+
+        let mut empty_start = board;
+        empty_start.start_player = Player::EMPTY;
+        assert_eq!(empty_start.history_error(), Some("Players Cannot Be Empty."));
+    }
+
+    #[test]
+    fn render_draws_an_ascii_grid() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 0, 8]);
+        assert_eq!(board.render(), "O..\n.X.\n..X");
+        assert_eq!(board.to_string(), board.render());
+    }
+
+    #[test]
+    fn from_matrix_rebuilds_a_consistent_board() {
+        use Player::{EMPTY as E, O, X};
+        let board = Board::from_matrix(vec![X, O, E, E, X, E, E, E, E], Difficulty::EASY).unwrap();
+        assert_eq!(board.get_current_turn(), O);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        assert_eq!(board.get_turn_count(), 3);
+        assert_eq!(board.matrix, vec![X, O, E, E, X, E, E, E, E]);
+
+        let board = Board::from_matrix(vec![O, O, O, X, X, E, X, E, E], Difficulty::EASY).unwrap();
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), O);
+        assert_eq!(board.get_current_turn(), X);
+    }
+
+    #[test]
+    fn make_move_rc_maps_coordinates_to_cells() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        board.make_move_rc(1, 2).unwrap();
+        assert_eq!(board.get_move_history(), vec![5]);
+        assert_eq!(board.matrix[5], Player::X);
+
+        assert_eq!(board.make_move_rc(3, 0), Err(MoveError::OutOfRange));
+        assert_eq!(board.make_move_rc(0, 3), Err(MoveError::OutOfRange));
+        assert_eq!(board.get_turn_count(), 1);
+    }
+
+    #[test]
+    fn hint_is_legal_and_leaves_the_board_alone() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[4, 0, 8]);
+        let snapshot = board.clone();
+
+        let hint = board.get_hint().unwrap();
+        assert!(board.get_available_moves().contains(&hint));
+        assert_eq!(board, snapshot);
+    }
+
+    #[test]
+    fn evaluate_moves_scores_the_winning_move_highest() {
+        // O to move: 5 wins, anything else lets X win on 2.
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 3, 1, 4, 8]);
+        let snapshot = board.clone();
+
+        let scores = board.evaluate_moves();
+        assert_eq!(scores.iter().map(|&(mv, _)| mv).collect::<Vec<usize>>(), vec![2, 5, 6, 7]);
+        let (best, best_score) = *scores.iter().max_by_key(|&&(_, score)| score).unwrap();
+        assert_eq!(best, 5);
+        assert!(scores.iter().all(|&(mv, score)| mv == best || score < best_score));
+        assert_eq!(board, snapshot);
+    }
+
+    #[test]
+    fn turn_and_winner_getters_follow_the_game() {
+        let mut board = Board::new(Player::O, Difficulty::EASY);
+        let mut expected_turn = Player::O;
+        for &mv in [0, 3, 1, 4, 2].iter() {
+            assert_eq!(board.get_current_turn(), expected_turn);
+            assert_eq!(board.get_winner(), Player::EMPTY);
+            board.make_move(mv).unwrap();
+            expected_turn = opponent(expected_turn);
+        }
+        assert_eq!(board.get_winner(), Player::O);
+        assert_eq!(board.get_current_turn(), Player::X);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_easy_game() {
+        let game = |seed: u64| {
+            let mut board = Board::new_seeded(Player::X, Difficulty::EASY, seed);
+            while let Some(mv) = board.get_next_move() {
+                board.make_move(mv).unwrap();
+            }
+            board.get_move_history()
+        };
+        assert_eq!(game(42), game(42));
+        assert_eq!(game(7), game(7));
+    }
+
+    #[test]
+    fn game_over_covers_wins_and_draws() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4]);
+        assert!(!board.is_game_over());
+        play(&mut board, &[2]);
+        assert!(board.is_game_over());
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &DRAWN_GAME);
+        assert!(board.is_game_over());
+    }
+
+    #[test]
+    fn move_history_is_in_play_order() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 0, 7]);
+        assert_eq!(board.get_move_history(), vec![4, 0, 7]);
+    }
+
+    #[test]
+    fn depth_one_search_still_blocks_a_threat() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 4, 1]);
+        assert_eq!(board.get_best_move_depth(1), Some(2));
+
+        board.set_max_depth(Some(1));
+        assert_eq!(board.get_best_move(), Some(2));
+    }
+
+    #[test]
+    fn depth_zero_is_searched_as_depth_one() {
+        for seed in 0..10 {
+            let mut board = Board::new_seeded(Player::X, Difficulty::DIFFICULT, seed);
+            play(&mut board, &[0, 3, 1, 4]);
+            assert_eq!(board.get_best_move_depth(0), Some(2));
+
+            board.set_max_depth(Some(0));
+            assert_eq!(board.get_best_move(), Some(2));
+        }
+    }
+
+    #[test]
+    fn heuristic_favours_the_player_one_move_from_winning() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 4, 1, 8]);
+        assert!(board.heuristic_score(&Player::X) > 0);
+        assert!(board.heuristic_score(&Player::O) < 0);
+
+        play(&mut board, &[2]);
+        assert_eq!(board.heuristic_score(&Player::X), 100);
+        assert_eq!(board.heuristic_score(&Player::O), -100);
+    }
+
+    #[test]
+    fn medium_takes_an_available_win() {
+        for seed in 0..20 {
+            // X to move with 2 winning, while O threatens 5.
+            let mut board = Board::new_seeded(Player::X, Difficulty::MEDIUM, seed);
+            play(&mut board, &[0, 3, 1, 4]);
+            assert_eq!(board.find_winning_move(Player::X), Some(2));
+            assert_eq!(board.get_next_move(), Some(2));
+        }
+    }
+
+    #[test]
+    fn medium_blocks_the_opponent_threat() {
+        for seed in 0..20 {
+            let mut board = Board::new_seeded(Player::X, Difficulty::MEDIUM, seed);
+            play(&mut board, &[0, 4, 1]);
+            assert_eq!(board.find_winning_move(Player::O), None);
+            assert_eq!(board.get_next_move(), Some(2));
+        }
+    }
+
+    #[test]
+    fn find_fork_move_finds_a_double_threat() {
+        // X holds 0 and 4 against O on 1 and 8; 3 and 6 both open two lines.
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 1, 4, 8]);
+        let matrix = board.matrix.clone();
+
+        assert_eq!(board.find_fork_move(Player::X), Some(3));
+        assert_eq!(board.fork_moves(Player::X), vec![3, 6]);
+        assert_eq!(board.matrix, matrix);
+    }
+
+    #[test]
+    fn opening_move_comes_from_the_book() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        for _ in 0..20 {
+            let opening = board.get_best_move().unwrap();
+            assert!([0, 2, 4, 6, 8].contains(&opening));
+            assert_eq!(board.last_search_nodes(), 0);
+        }
+    }
+
+    #[test]
+    fn root_search_skips_symmetric_openings() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        let scores = board.search_moves(None);
+        assert_eq!(scores.len(), 9);
+        assert!(scores.iter().all(|&(_, score)| score == 0));
+
+        // Only a corner, an edge and the center are actually searched.
+        let mut search = Search::new(None, Arc::new(AtomicBool::new(false)));
+        for &mv in [0, 1, 4].iter() {
+            board.apply_move(mv);
+            let mover = board.turn;
+            minimax(&mut board, &mover, 0, i32::MIN, i32::MAX, &mut search);
+            board.revert_move();
+        }
+        assert_eq!(board.last_search_nodes(), search.nodes);
+    }
+
+    #[test]
+    fn bitboard_agrees_with_the_matrix_on_every_line() {
+        for line in get_lines() {
+            for &player in [Player::X, Player::O].iter() {
+                let mut board = Board::new(Player::X, Difficulty::EASY);
+                for &cell in line.iter() {
+                    board.matrix[cell] = player;
+                }
+                board.sync_bitboard();
+
+                assert_eq!(board.bitboard.winner(), player);
+                assert_eq!(board.find_completed_line(), Some(line.clone()));
+                for position in 0..9 {
+                    let through = [(0, 1), (1, 0), (1, 1), (1, -1)].iter()
+                        .any(|&(d_row, d_col)| board.matrix[position] == player
+                            && board.run_length(position, d_row, d_col) >= 3);
+                    assert_eq!(board.bitboard.has_line_through(position, player), through);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn turn_count_grows_until_the_board_is_full() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        for (index, &mv) in DRAWN_GAME.iter().enumerate() {
+            assert_eq!(board.get_turn_count(), index);
+            assert!(!board.is_board_full());
+            board.make_move(mv).unwrap();
+        }
+        assert_eq!(board.get_turn_count(), 9);
+        assert!(board.is_board_full());
+    }
+
+    #[test]
+    fn set_difficulty_changes_later_moves_only() {
+        let mut board = Board::new_seeded(Player::X, Difficulty::EASY, 3);
+        play(&mut board, &[0, 4, 1]);
+        board.set_difficulty(Difficulty::MEDIUM);
+        assert_eq!(board.get_difficulty(), Difficulty::MEDIUM);
+        board.set_difficulty(Difficulty::DIFFICULT);
+        assert_eq!(board.get_difficulty(), Difficulty::DIFFICULT);
+        assert_eq!(board.get_move_history(), vec![0, 4, 1]);
+
+        // O has to block on 2, which DIFFICULT always does.
+        assert_eq!(board.get_next_move(), Some(2));
+    }
+
+    #[test]
+    fn make_move_reports_each_failure_as_a_move_error() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.make_move(9), Err(MoveError::OutOfRange));
+        play(&mut board, &[0]);
+        assert_eq!(board.make_move(0), Err(MoveError::AlreadyFilled));
+        play(&mut board, &[3, 1, 4, 2]);
+        assert_eq!(board.make_move(5), Err(MoveError::GameOver));
+
+        assert_eq!(move_error_message(MoveError::OutOfRange),
+            "Illegal Position Supplied. Try Again.");
+        assert_eq!(MoveError::GameOver.to_string(), "Game is already over.");
+    }
+
+    #[test]
+    fn checked_next_move_is_legal_until_the_game_ends() {
+        for difficulty in [Difficulty::EASY, Difficulty::MEDIUM, Difficulty::DIFFICULT,
+            Difficulty::IMPOSSIBLE] {
+            let mut board = Board::new(Player::X, difficulty);
+            play(&mut board, &[4, 0]);
+            let mv = board.get_next_move_checked().unwrap();
+            assert!(board.is_legal_move(mv));
+
+            play(&mut board, &[8, 1, 7, 6, 2, 5, 3]);
+            assert_eq!(board.get_next_move_checked(), Err(MoveError::GameOver));
+        }
+    }
+
+    #[test]
+    fn notation_round_trips_every_cell() {
+        for pos in 0..9 {
+            let notation = move_to_notation(pos).unwrap();
+            assert_eq!(notation_to_move(&notation).unwrap(), pos);
+        }
+        assert_eq!(move_to_notation(0), Some(String::from("a1")));
+        assert_eq!(move_to_notation(5), Some(String::from("c2")));
+        assert_eq!(move_to_notation(9), None);
+        assert_eq!(parse_notation(" B3 ", 3), Some(7));
+    }
+
+    #[test]
+    fn malformed_notation_is_rejected() {
+        for &notation in ["d1", "a4", "a", "a11", "1a", ""].iter() {
+            assert_eq!(parse_notation(notation, 3), None, "{:?}", notation);
+        }
+        for &notation in ["a0", "a01", "a+1", "é1"].iter() {
+            assert_eq!(parse_notation(notation, 12), None, "{:?}", notation);
+        }
+    }
+
+    #[test]
+    fn notation_follows_the_board_size() {
+        assert_eq!(parse_notation("d1", 4), Some(3));
+        assert_eq!(parse_notation("a11", 12), Some(120));
+
+        let mut board = Board::new_sized(Player::X, Difficulty::EASY, 4);
+        board.make_move_notation("a2").unwrap();
+        board.make_move_notation("d4").unwrap();
+        assert_eq!(board.get_move_history(), vec![4, 15]);
+    }
+
+    #[test]
+    fn difficult_against_difficult_is_always_a_draw() {
+        for seed in 0..10 {
+            for &start_player in [Player::X, Player::O].iter() {
+                let result = play_out(start_player, Difficulty::DIFFICULT,
+                    Difficulty::DIFFICULT, seed);
+                assert_eq!(result, State::DRAW);
+            }
+        }
+    }
+
+    #[test]
+    fn simulate_many_tallies_every_game() {
+        let counts = simulate_many(20, Difficulty::DIFFICULT, Difficulty::EASY, 11);
+        assert_eq!(counts.iter().sum::<u32>(), 20);
+        assert_eq!(counts[1], 0);
+        assert!(counts[0] > 10);
+    }
+
+    // Plays every possible reply for the side that is not `ai` and checks
+    // that `ai` never ends up losing.
+    fn assert_never_loses(board: &Board, ai: Player) {
+        if board.is_game_over() {
+            assert_ne!(board.get_winner(), opponent(ai), "lost {:?}", board.get_move_history());
+            return;
+        }
+
+        if board.get_current_turn() == ai {
+            let mut next = board.clone();
+            let mv = next.get_next_move().unwrap();
+            next.make_move(mv).unwrap();
+            assert_never_loses(&next, ai);
+        } else {
+            for mv in board.get_available_moves() {
+                assert_never_loses(&board.try_move(mv).unwrap(), ai);
+            }
+        }
+    }
+
+    #[test]
+    fn impossible_never_loses() {
+        assert_never_loses(&Board::new_seeded(Player::X, Difficulty::IMPOSSIBLE, 5), Player::X);
+        assert_never_loses(&Board::new_seeded(Player::X, Difficulty::IMPOSSIBLE, 5), Player::O);
+    }
+
+    fn self_play(mut board: Board) -> Vec<usize> {
+        while let Some(mv) = board.get_next_move() {
+            board.make_move(mv).unwrap();
+        }
+        board.get_move_history()
+    }
+
+    #[test]
+    fn mistake_rate_extremes_match_the_fixed_tiers() {
+        for seed in 0..5 {
+            let mut never = Board::new_seeded(Player::X, Difficulty::EASY, seed);
+            never.set_mistake_rate(0.0);
+            let difficult = Board::new_seeded(Player::X, Difficulty::DIFFICULT, seed);
+            assert_eq!(self_play(never), self_play(difficult));
+
+            let mut always = Board::new_seeded(Player::X, Difficulty::DIFFICULT, seed);
+            always.set_mistake_rate(1.0);
+            let easy = Board::new_seeded(Player::X, Difficulty::EASY, seed);
+            assert_eq!(self_play(always), self_play(easy));
+        }
+    }
+
+    #[test]
+    fn set_difficulty_clears_the_mistake_rate() {
+        for seed in 0..5 {
+            let mut board = Board::new_seeded(Player::X, Difficulty::DIFFICULT, seed);
+            board.set_mistake_rate(0.0);
+            board.set_difficulty(Difficulty::EASY);
+            assert_eq!(board.mistake_rate, None);
+            let easy = Board::new_seeded(Player::X, Difficulty::EASY, seed);
+            assert_eq!(self_play(board), self_play(easy));
+        }
+    }
+
+    #[test]
+    fn mistake_rate_is_clamped() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        board.set_mistake_rate(2.5);
+        assert_eq!(board.mistake_rate, Some(MistakeRate(1.0)));
+        board.set_mistake_rate(-1.0);
+        assert_eq!(board.mistake_rate, Some(MistakeRate(0.0)));
+        board.set_mistake_rate(f64::NAN);
+        assert_eq!(board.mistake_rate, Some(MistakeRate(0.0)));
+    }
+
+    #[test]
+    fn last_move_is_the_most_recent_cell() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.get_last_move(), None);
+        play(&mut board, &[4, 6]);
+        assert_eq!(board.get_last_move(), Some(6));
+    }
+
+    #[test]
+    fn count_pieces_tallies_each_player() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 0, 8]);
+        let counts = board.count_pieces();
+        assert_eq!(counts, vec![2, 1, 6]);
+        assert_eq!(counts.iter().sum::<usize>(), 9);
+    }
+
+    #[test]
+    fn is_legal_move_checks_without_changing_the_board() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0]);
+        let snapshot = board.clone();
+        assert!(board.is_legal_move(4));
+        assert!(!board.is_legal_move(0));
+        assert!(!board.is_legal_move(9));
+        assert_eq!(board, snapshot);
+
+        play(&mut board, &[3, 1, 4, 2]);
+        assert!(!board.is_legal_move(5));
+    }
+
+    #[test]
+    fn try_move_leaves_the_original_untouched() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4]);
+        let snapshot = board.clone();
+
+        let next = board.try_move(2).unwrap();
+        assert_eq!(board, snapshot);
+        assert_eq!(next.get_move_history(), vec![0, 3, 1, 4, 2]);
+        assert_eq!(next.get_board_state(), State::RESULTED);
+        assert_eq!(next.get_winner(), Player::X);
+
+        assert_eq!(board.try_move(0).unwrap_err(), MoveError::AlreadyFilled);
+    }
+
+    #[test]
+    fn principal_variation_plays_out_the_forced_win() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 1, 4, 8]);
+        let snapshot = board.clone();
+
+        // Fork, forced block, win.
+        let variation = board.principal_variation();
+        assert_eq!(variation.len(), 3);
+        assert_eq!(board, snapshot);
+
+        play(&mut board, &variation);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::X);
+    }
+
+    #[test]
+    fn four_quarter_turns_restore_the_board() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 4, 5]);
+        let turned = board.rotate_90().rotate_90().rotate_90().rotate_90();
+        assert_eq!(turned, board);
+        assert_eq!(board.mirror_horizontal().mirror_horizontal(), board);
+    }
+
+    #[test]
+    fn rotating_a_row_win_gives_a_column_win() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+
+        let turned = board.rotate_90();
+        assert_eq!(turned.get_winning_line(), Some(vec![2, 5, 8]));
+        assert_eq!(turned.get_board_state(), State::RESULTED);
+        assert_eq!(turned.get_winner(), Player::X);
+        assert_eq!(turned.get_move_history(), vec![2, 1, 5, 4, 8]);
+
+        let mirrored = board.mirror_horizontal();
+        assert_eq!(mirrored.get_winning_line(), Some(vec![0, 1, 2]));
+        assert_eq!(mirrored.get_move_history(), vec![2, 5, 1, 4, 0]);
+    }
+
+    #[test]
+    fn canonical_key_is_shared_by_symmetric_positions() {
+        let key_after = |moves: &[usize]| {
+            let mut board = Board::new(Player::X, Difficulty::EASY);
+            play(&mut board, moves);
+            board.canonical_key()
+        };
+        assert_eq!(key_after(&[0]), key_after(&[2]));
+        assert_eq!(key_after(&[0]), key_after(&[8]));
+        assert_eq!(key_after(&[0, 1]), key_after(&[6, 3]));
+        assert_ne!(key_after(&[0]), key_after(&[1]));
+        assert_ne!(key_after(&[0]), key_after(&[4]));
+        assert_ne!(key_after(&[0, 1]), key_after(&[0, 5]));
+    }
+
+    #[test]
+    fn last_search_nodes_counts_each_search_afresh() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        let mut full_nodes = 0;
+        full_minimax(&mut board, Player::O, 0, &mut full_nodes);
+        assert_eq!(full_nodes, 549_946);
+
+        // Pruning, the transposition table and symmetry cut that to a few
+        // thousand; a change here means the search itself changed.
+        board.evaluate_moves();
+        assert_eq!(board.last_search_nodes(), 5544);
+        board.evaluate_moves();
+        assert_eq!(board.last_search_nodes(), 5544);
+    }
+
+    #[test]
+    fn start_player_is_remembered() {
+        let mut board = Board::new(Player::O, Difficulty::EASY);
+        play(&mut board, &[4, 0, 8]);
+        assert_eq!(board.get_start_player(), Player::O);
+
+        board.reset(Player::X);
+        assert_eq!(board.get_start_player(), Player::X);
+    }
+
+    #[test]
+    fn empty_start_player_is_read_as_x() {
+        let mut board = Board::new(Player::EMPTY, Difficulty::EASY);
+        assert_eq!(board.get_start_player(), Player::X);
+        for &(mv, next) in [(4, Player::O), (0, Player::X), (8, Player::O)].iter() {
+            board.make_move(mv).unwrap();
+            assert_eq!(board.get_current_turn(), next);
+        }
+        assert_eq!(board.matrix[4], Player::X);
+        assert_eq!(board.matrix[0], Player::O);
+
+        let mut board = Board::new_sized(Player::EMPTY, Difficulty::EASY, 4);
+        board.reset(Player::EMPTY);
+        assert_eq!(board.get_current_turn(), Player::X);
+
+        assert_eq!(Board::try_new(Player::O, Difficulty::EASY).unwrap().get_current_turn(),
+            Player::O);
+    }
+
+    #[test]
+    fn go_to_move_scrubs_back_and_forward() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        let finished = board.clone();
+        let mut early = Board::new(Player::X, Difficulty::EASY);
+        play(&mut early, &[0, 3]);
+
+        board.go_to_move(2).unwrap();
+        assert_eq!(board.matrix, early.matrix);
+        assert_eq!(board.get_current_turn(), Player::X);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        assert_eq!(board.get_winner(), Player::EMPTY);
+
+        board.go_to_move(5).unwrap();
+        assert_eq!(board, finished);
+    }
+
+    #[test]
+    fn go_to_move_stops_at_an_illegal_redo_move() {
+        // X wins on 2, so the O move stacked after it can never be replayed.
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        board.go_to_move(0).unwrap();
+        board.redo_stack.insert(0, 5);
+
+        board.go_to_move(6).unwrap();
+        assert_eq!(board.get_move_history(), vec![0, 3, 1, 4, 2]);
+        assert_eq!(board.redo_stack, vec![5]);
+        assert_eq!(board.get_move_timestamps().len(), 5);
+    }
+
+    #[test]
+    fn history_error_checks_the_redo_stack() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 0]);
+        board.undo_move().unwrap();
+        assert_eq!(board.history_error(), None);
+
+        for redo_stack in [vec![99], vec![4], vec![0, 0]] {
+            let mut broken = board.clone();
+            broken.redo_stack = redo_stack;
+            assert_eq!(broken.history_error(), Some("Redo Stack Is Invalid."));
+        }
+    }
+
+    #[test]
+    fn redo_replays_the_undone_move() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 0]);
+        let intermediate = board.clone();
+        play(&mut board, &[8]);
+
+        board.undo_move().unwrap();
+        board.undo_move().unwrap();
+        assert_eq!(board.redo().unwrap(), 0);
+        assert_eq!(board.matrix, intermediate.matrix);
+        assert_eq!(board.get_current_turn(), Player::X);
+        assert_eq!(board.redo_stack, vec![8]);
+
+        play(&mut board, &[2]);
+        assert!(board.redo_stack.is_empty());
+    }
+
+    #[test]
+    fn transforms_map_the_redo_stack() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 1]);
+        board.undo_move().unwrap();
+
+        let mut turned = board.rotate_90();
+        assert_eq!(turned.redo().unwrap(), 5);
+        assert_eq!(turned.matrix[5], Player::O);
+    }
+
+    #[test]
+    fn default_board_is_a_fresh_difficult_game() {
+        assert_eq!(Board::default(), Board::new(Player::X, Difficulty::DIFFICULT));
+    }
+
+    #[test]
+    fn enums_round_trip_through_u8() {
+        for &player in [Player::EMPTY, Player::X, Player::O].iter() {
+            assert_eq!(Player::from_u8(player.as_u8()), Some(player));
+        }
+        for value in 0..4 {
+            assert_eq!(Difficulty::from_u8(value).unwrap().as_u8(), value);
+        }
+        assert_eq!(Player::from_u8(3), None);
+        assert_eq!(Difficulty::from_u8(4), None);
+        assert_eq!(Difficulty::from_u8(255), None);
+    }
+
+    #[test]
+    fn bytes_round_trip_the_position() {
+        use Variant::{MISERE, STANDARD};
+        let games: [(Player, Variant, bool, &[usize]); 7] = [
+            (Player::X, STANDARD, false, &[]),
+            (Player::X, STANDARD, false, &[4, 0, 8]),
+            (Player::O, STANDARD, false, &[4, 0]),
+            (Player::X, STANDARD, false, &[0, 3, 1, 4, 2]),
+            (Player::X, STANDARD, false, &DRAWN_GAME),
+            // Completing the line loses, so O is the winner.
+            (Player::X, MISERE, false, &[0, 3, 1, 4, 2]),
+            // Drawn with a cell still empty.
+            (Player::X, STANDARD, true, &[0, 4, 2, 1, 7, 6, 3, 5]),
+        ];
+        for &(start_player, variant, early_draw, moves) in games.iter() {
+            let mut board = Board::new(start_player, Difficulty::MEDIUM);
+            board.set_variant(variant);
+            board.set_early_draw(early_draw);
+            play(&mut board, moves);
+            let bytes = board.to_bytes().unwrap();
+            assert_eq!(bytes.len(), 15);
+
+            let restored = Board::from_bytes(&bytes).unwrap();
+            assert!(restored.same_position(&board), "after {:?}", moves);
+            assert_eq!(restored.get_board_state(), board.get_board_state());
+            assert_eq!(restored.get_winner(), board.get_winner());
+            assert_eq!(restored.get_variant(), variant);
+            assert_eq!(restored.early_draw, early_draw);
+            assert_eq!(restored.get_difficulty(), Difficulty::MEDIUM);
+            assert_eq!(restored.to_bytes().unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn center_opening_is_a_theoretical_draw() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4]);
+        let snapshot = board.clone();
+        assert!(board.is_theoretically_drawn());
+        assert_eq!(board, snapshot);
+    }
+
+    #[test]
+    fn forced_win_is_not_a_theoretical_draw() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 1, 4, 8]);
+        assert!(!board.is_theoretically_drawn());
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &DRAWN_GAME);
+        assert!(board.is_theoretically_drawn());
+    }
+
+    #[test]
+    fn theoretical_result_names_the_forced_winner() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 1, 4, 8]);
+        let snapshot = board.clone();
+        assert_eq!(board.theoretical_result(), Player::X);
+        assert_eq!(board, snapshot);
+
+        // O to move, but whatever O does X completes the fork.
+        play(&mut board, &[6]);
+        assert_eq!(board.theoretical_result(), Player::X);
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4]);
+        assert_eq!(board.theoretical_result(), Player::EMPTY);
+    }
+
+    #[test]
+    fn find_winning_move_spots_the_completing_cell() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4]);
+        let snapshot = board.clone();
+        assert_eq!(board.find_winning_move(Player::X), Some(2));
+        assert_eq!(board.find_winning_move(Player::O), Some(5));
+        assert_eq!(board, snapshot);
+    }
+
+    #[test]
+    fn find_blocking_move_spots_the_opponent_threat() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 4, 1]);
+        let snapshot = board.clone();
+        assert_eq!(board.find_blocking_move(), Some(2));
+        assert_eq!(board.find_winning_move(Player::O), None);
+        assert_eq!(board, snapshot);
+
+        // Blocking on 2 gives O the diagonal threat on 6 for X to answer.
+        play(&mut board, &[2]);
+        assert_eq!(board.find_blocking_move(), Some(6));
+    }
+
+    #[test]
+    fn result_message_covers_every_outcome() {
+        let message_after = |start_player: Player, moves: &[usize]| {
+            let mut board = Board::new(start_player, Difficulty::EASY);
+            play(&mut board, moves);
+            board.get_result_message()
+        };
+        assert_eq!(message_after(Player::X, &[0, 3, 1, 4, 2]), "X wins");
+        assert_eq!(message_after(Player::O, &[0, 3, 1, 4, 2]), "O wins");
+        assert_eq!(message_after(Player::X, &DRAWN_GAME), "Draw");
+        assert_eq!(message_after(Player::X, &[4]), "In progress");
+    }
+
+    #[test]
+    fn move_callback_fires_once_per_successful_move() {
+        let calls: Rc<RefCell<Vec<(u32, u32)>>> = Rc::default();
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        let recorded = calls.clone();
+        board.on_move = Ignored(JsCallback(Some(Rc::new(move |position, status| {
+            recorded.borrow_mut().push((position, status));
+        }))));
+
+        play(&mut board, &[0, 3, 1, 4]);
+        assert_eq!(board.make_move(0), Err(MoveError::AlreadyFilled));
+        board.try_move(2).unwrap();
+        play(&mut board, &[2]);
+        assert_eq!(board.make_move(5), Err(MoveError::GameOver));
+
+        let in_progress = State::INPROGRESS as u32;
+        assert_eq!(*calls.borrow(), vec![(0, in_progress), (3, in_progress), (1, in_progress),
+            (4, in_progress), (2, State::RESULTED as u32)]);
+    }
+
+    #[test]
+    fn moves_work_without_a_callback() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert!(board.on_move.0.0.is_none());
+        play(&mut board, &DRAWN_GAME);
+        assert_eq!(board.get_board_state(), State::DRAW);
+    }
+
+    #[test]
+    fn threats_lists_each_winning_cell() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 4, 1]);
+        assert_eq!(board.threats(Player::X), vec![2]);
+        assert!(board.threats(Player::O).is_empty());
+
+        // X on 0, 4 and 6 against O on 1 and 8 threatens both 2 and 3.
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 1, 4, 8, 6]);
+        assert_eq!(board.threats(Player::X), vec![2, 3]);
+    }
+
+    #[test]
+    fn misere_line_loses_for_whoever_completes_it() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        board.set_variant(Variant::MISERE);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        assert_eq!(board.get_board_state(), State::RESULTED);
+        assert_eq!(board.get_winner(), Player::O);
+    }
+
+    #[test]
+    fn misere_ai_avoids_completing_a_line() {
+        for seed in 0..10 {
+            let mut board = Board::new_seeded(Player::X, Difficulty::DIFFICULT, seed);
+            board.set_variant(Variant::MISERE);
+            play(&mut board, &[0, 4, 1, 5]);
+            let mv = board.get_best_move().unwrap();
+            assert_ne!(mv, 2);
+            board.make_move(mv).unwrap();
+            assert_ne!(board.get_winner(), Player::O);
+        }
+    }
+
+    #[test]
+    fn misere_has_no_winning_cells() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        board.set_variant(Variant::MISERE);
+        play(&mut board, &[0, 3, 1, 4]);
+        assert_eq!(board.find_winning_move(Player::X), None);
+        assert_eq!(board.find_blocking_move(), None);
+        assert!(board.threats(Player::X).is_empty());
+        assert_eq!(board.fork_count(Player::X), 0);
+        assert!(board.threat_map().iter().all(|&code| code == 0));
+
+        // Completing the line on 2 is a loss, not the win it would be above.
+        board.make_move(2).unwrap();
+        assert_eq!(board.get_winner(), Player::O);
+    }
+
+    #[test]
+    fn get_cell_reads_by_coordinate() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[5, 6]);
+        assert_eq!(board.get_cell(1, 2).unwrap(), Player::X);
+        assert_eq!(board.get_cell(2, 0).unwrap(), Player::O);
+        assert_eq!(board.get_cell(0, 0).unwrap(), Player::EMPTY);
+    }
+
+    // Ticks one second per reading, starting from 1000 on each test thread.
+    fn fake_clock() -> f64 {
+        thread_local!(static NOW: Cell<f64> = const { Cell::new(0.0) });
+        NOW.with(|now| {
+            now.set(now.get() + 1000.0);
+            now.get()
+        })
+    }
+
+    #[test]
+    fn timestamps_follow_the_move_history() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        board.set_clock(fake_clock);
+        play(&mut board, &[4, 0, 8]);
+        assert_eq!(board.get_move_timestamps(), vec![1000.0, 2000.0, 3000.0]);
+
+        board.undo_move().unwrap();
+        assert_eq!(board.get_move_timestamps(), vec![1000.0, 2000.0]);
+        board.redo().unwrap();
+        assert_eq!(board.get_move_timestamps(), vec![1000.0, 2000.0, 4000.0]);
+
+        // Each jump reads the clock once, for any moves it brings back.
+        board.go_to_move(1).unwrap();
+        assert_eq!(board.get_move_timestamps(), vec![1000.0]);
+        board.go_to_move(3).unwrap();
+        assert_eq!(board.get_move_timestamps(), vec![1000.0, 6000.0, 6000.0]);
+        assert_eq!(board.get_move_timestamps().len(), board.get_move_history().len());
+    }
+
+    #[test]
+    fn ranked_moves_put_the_best_move_first() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 3, 1, 4, 8]);
+        let snapshot = board.clone();
+
+        let ranking = board.ranked_moves();
+        assert_eq!(ranking[0], 5);
+        // Blocking on 2 forks and wins later; 6 and 7 let X win on 2.
+        assert_eq!(ranking, vec![5, 2, 6, 7]);
+        assert_eq!(board, snapshot);
+    }
+
+    #[test]
+    fn checked_best_move_refuses_finished_games() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        let snapshot = board.clone();
+        assert_eq!(board.get_best_move_checked(), Err(MoveError::GameOver));
+        assert_eq!(board, snapshot);
+
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 4, 1]);
+        assert_eq!(board.get_best_move_checked(), Ok(2));
+    }
+
+    #[test]
+    fn evaluate_last_move_flags_a_missed_win() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 3, 1, 4, 8]);
+        let snapshot = board.clone();
+        assert!(board.evaluate_last_move() > 0);
+        assert_eq!(board, snapshot);
+
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        assert_eq!(board.evaluate_last_move(), 0);
+        assert_eq!(Board::new(Player::X, Difficulty::DIFFICULT).evaluate_last_move(), 0);
+    }
+
+    #[test]
+    fn center_and_corners_outscore_edges() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[4, 1, 0, 3, 2, 5]);
+        assert_eq!(board.positional_score(Player::X), 7 - 3);
+        assert!(board.positional_score(Player::X) > board.positional_score(Player::O));
+        assert_eq!(board.positional_score(Player::O), -4);
+        assert_eq!(board.positional_score(Player::EMPTY), 0);
+    }
+
+    #[test]
+    fn dead_draw_is_spotted_before_the_board_fills() {
+        // XOX / XOO / OX. with X to move: no line can be completed on 8.
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 4, 2, 1, 7, 6, 3]);
+        assert!(!board.is_dead_draw());
+        play(&mut board, &[5]);
+        assert!(board.is_dead_draw());
+        assert!(!board.is_board_full());
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+
+        board.set_early_draw(true);
+        assert_eq!(board.get_board_state(), State::DRAW);
+    }
+
+    #[test]
+    fn moves_off_the_diagonals_only_complete_their_own_lines() {
+        // X holds two of the main diagonal with O on the third cell.
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 8, 4, 5, 1]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        play(&mut board, &[3, 2]);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(board.get_winning_line(), Some(vec![0, 1, 2]));
+
+        // The same on a 4x4 board, where the run-length check is used.
+        let mut board = Board::new_with_win_length(Player::X, Difficulty::EASY, 4, 3);
+        play(&mut board, &[0, 10, 5, 15, 1]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        play(&mut board, &[12, 2]);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(board.get_winning_line(), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn empty_count_drops_with_each_move() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        for (index, &mv) in DRAWN_GAME.iter().enumerate() {
+            assert_eq!(board.get_empty_count(), 9 - index);
+            board.make_move(mv).unwrap();
+        }
+        assert_eq!(board.get_empty_count(), 0);
+    }
+
+    #[test]
+    fn transcript_is_reproducible_from_the_seed() {
+        for seed in 0..5 {
+            let transcript = play_out_transcript(Player::X, Difficulty::EASY, Difficulty::MEDIUM,
+                seed);
+            assert_eq!(transcript, play_out_transcript(Player::X, Difficulty::EASY,
+                Difficulty::MEDIUM, seed));
+
+            let mut board = Board::new(Player::X, Difficulty::EASY);
+            play(&mut board, &transcript);
+            assert!(board.is_game_over());
+            assert_eq!(board.get_board_state(),
+                play_out(Player::X, Difficulty::EASY, Difficulty::MEDIUM, seed));
+        }
+    }
+
+    #[test]
+    fn same_position_ignores_move_order() {
+        let mut first = Board::new(Player::X, Difficulty::EASY);
+        play(&mut first, &[0, 4, 8]);
+        let mut second = Board::new(Player::X, Difficulty::EASY);
+        play(&mut second, &[8, 4, 0]);
+
+        assert!(first.same_position(&second));
+        assert_ne!(first, second);
+
+        play(&mut second, &[1]);
+        assert!(!first.same_position(&second));
+    }
+
+    #[test]
+    fn restore_brings_back_the_snapshot() {
+        let mut board = Board::new(Player::X, Difficulty::MEDIUM);
+        play(&mut board, &[4]);
+        let snapshot = board.snapshot();
+
+        play(&mut board, &[0, 8, 2]);
+        board.set_difficulty(Difficulty::EASY);
+        board.restore(&snapshot);
+        assert_eq!(board, snapshot);
+    }
+
+    #[test]
+    fn classic_board_has_eight_lines_of_three() {
+        let lines = get_lines();
+        assert_eq!(lines.len(), 8);
+        for line in lines.iter() {
+            let cells: HashSet<usize> = line.iter().cloned().collect();
+            assert_eq!(line.len(), 3);
+            assert_eq!(cells.len(), 3);
+            assert!(line.iter().all(|&cell| cell < 9));
+        }
+        assert_eq!(winning_lines(4, 3).len(), 24);
+    }
+
+    #[test]
+    fn from_moves_replays_a_legal_game() {
+        let board = Board::from_moves(vec![4, 0, 8], Player::O, Difficulty::EASY).unwrap();
+        assert_eq!(board.get_move_history(), vec![4, 0, 8]);
+        assert_eq!(board.matrix[4], Player::O);
+        assert_eq!(board.get_current_turn(), Player::X);
+    }
+
+    #[test]
+    fn from_moves_stops_at_the_first_illegal_move() {
+        let import = |moves: Vec<usize>| Board::from_moves(moves, Player::X, Difficulty::EASY);
+        assert_eq!(import(vec![4, 0, 4]).unwrap_err(), MoveError::AlreadyFilled);
+        assert_eq!(import(vec![0, 3, 1, 4, 2, 5]).unwrap_err(), MoveError::GameOver);
+        assert_eq!(import(vec![9]).unwrap_err(), MoveError::OutOfRange);
+    }
+
+    #[test]
+    fn stopped_search_still_returns_a_legal_move() {
+        let mut board = Board::new_sized(Player::X, Difficulty::DIFFICULT, 4);
+        play(&mut board, &[5, 0]);
+        board.stop();
+        let mv = board.get_best_move().unwrap();
+        assert!(board.is_legal_move(mv));
+        assert!(board.search_stats.0.stopped);
+        assert!(board.last_search_nodes() <= 14);
+
+        // The flag is used up by the stopped search, and stop_handle sets the
+        // same one.
+        assert!(!board.stop_handle().load(Ordering::Relaxed));
+        board.stop_handle().store(true, Ordering::Relaxed);
+        let mv = board.get_best_move().unwrap();
+        assert!(board.is_legal_move(mv));
+        assert!(board.search_stats.0.stopped);
+    }
+
+    #[test]
+    fn copies_have_a_stop_flag_of_their_own() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[4, 0]);
+        board.snapshot().stop();
+        board.rotate_90().stop_handle().store(true, Ordering::Relaxed);
+        assert!(!board.stop_handle().load(Ordering::Relaxed));
+
+        // A hint searches a copy, so it leaves a pending stop for the game.
+        board.stop();
+        board.get_hint().unwrap();
+        assert!(board.stop_handle().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn stop_leaves_analysis_searches_alone() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[4, 0]);
+        let scores = board.evaluate_moves();
+
+        board.stop();
+        assert_eq!(board.theoretical_result(), Player::EMPTY);
+        assert!(board.is_theoretically_drawn());
+        assert_eq!(board.evaluate_moves(), scores);
+        assert!(!board.search_stats.0.stopped);
+
+        // The flag is still waiting for the next AI move search.
+        assert!(board.stop_handle().load(Ordering::Relaxed));
+        board.get_best_move().unwrap();
+        assert!(board.search_stats.0.stopped);
+    }
+
+    #[test]
+    fn progress_is_reported_once_per_root_move() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[4, 0]);
+        let mut reports: Vec<(usize, usize)> = vec![];
+        board.get_best_move_with_progress(&mut |done, total| reports.push((done, total)));
+        assert_eq!(reports, (1..=7).map(|done| (done, 7)).collect::<Vec<_>>());
+
+        // Answers from the cache or the opening book need no search and
+        // report everything as done in one go.
+        reports.clear();
+        board.get_best_move_with_progress(&mut |done, total| reports.push((done, total)));
+        assert_eq!(reports, vec![(7, 7)]);
+
+        reports.clear();
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        board.get_best_move_with_progress(&mut |done, total| reports.push((done, total)));
+        assert_eq!(reports, vec![(9, 9)]);
+    }
+
+    #[test]
+    fn fork_count_counts_every_forking_cell() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 1, 4, 8]);
+        assert_eq!(board.fork_count(Player::X), 2);
+        assert_eq!(board.fork_count(Player::O), 0);
+        assert_eq!(Board::new(Player::X, Difficulty::EASY).fork_count(Player::X), 0);
+    }
+
+    #[test]
+    fn make_move_status_reports_the_resulting_state() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.make_move_status(0), Ok(State::INPROGRESS));
+        play(&mut board, &[3, 1, 4]);
+        assert_eq!(board.make_move_status(2), Ok(State::RESULTED));
+        assert_eq!(board.make_move_status(5), Err(MoveError::GameOver));
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &DRAWN_GAME[..8]);
+        assert_eq!(board.make_move_status(DRAWN_GAME[8]), Ok(State::DRAW));
+    }
+
+    #[test]
+    fn player_at_turn_alternates_from_the_starter() {
+        let mut board = Board::new(Player::O, Difficulty::EASY);
+        play(&mut board, &[4, 0, 8]);
+        assert_eq!(board.get_player_at_turn(0).unwrap(), Player::O);
+        assert_eq!(board.get_player_at_turn(1).unwrap(), Player::X);
+        assert_eq!(board.get_player_at_turn(2).unwrap(), Player::O);
+        for n in 0..3 {
+            let cell = board.get_move_history()[n];
+            assert_eq!(board.get_player_at_turn(n).unwrap(), board.matrix[cell]);
+        }
+    }
+
+    #[test]
+    fn ai_player_answers_the_human_move() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        board.set_ai_player(Player::O);
+        play(&mut board, &[0, 4]);
+
+        // O has to block X on 2.
+        assert_eq!(board.make_move_and_respond(1).unwrap(), Some(2));
+        assert_eq!(board.get_move_history(), vec![0, 4, 1, 2]);
+        assert_eq!(board.get_current_turn(), Player::X);
+    }
+
+    #[test]
+    fn winning_human_move_gets_no_reply() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        board.set_ai_player(Player::O);
+        play(&mut board, &[0, 3, 1, 4]);
+        assert_eq!(board.make_move_and_respond(2).unwrap(), None);
+        assert_eq!(board.get_winner(), Player::X);
+        assert_eq!(board.get_turn_count(), 5);
+    }
+
+    #[test]
+    fn weighted_random_favours_the_center() {
+        let mut board = Board::new_seeded(Player::X, Difficulty::EASY, 11);
+        board.set_weighted_random(true);
+        let mut counts = [0; 9];
+        for _ in 0..3000 {
+            counts[board.get_random_move().unwrap()] += 1;
+        }
+
+        // Weights 3, 2 and 1 out of 15 expect 600, 400 and 200 picks.
+        assert!(counts[4] > 2 * counts[1], "{:?}", counts);
+        assert!(counts[0] > counts[1], "{:?}", counts);
+        assert!(counts.iter().all(|&count| count > 0), "{:?}", counts);
+    }
+
+    #[test]
+    fn debug_state_format() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.debug_state(), "turn=X status=INPROGRESS winner=EMPTY moves=[]");
+
+        play(&mut board, &[4, 0, 8]);
+        assert_eq!(board.debug_state(), "turn=O status=INPROGRESS winner=EMPTY moves=[4,0,8]");
+
+        play(&mut board, &[1, 2, 3, 6]);
+        assert_eq!(board.debug_state(),
+            "turn=O status=RESULTED winner=X moves=[4,0,8,1,2,3,6]");
+    }
+
+    #[test]
+    fn full_board_without_a_line_is_drawn() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &DRAWN_GAME[..8]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        board.make_move(DRAWN_GAME[8]).unwrap();
+        assert_eq!(board.get_board_state(), State::DRAW);
+        assert_eq!(board.get_winner(), Player::EMPTY);
+        assert!(board.get_available_moves().is_empty());
+    }
+
+    #[test]
+    fn dead_position_is_drawn_early_when_enabled() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        board.set_early_draw(true);
+        play(&mut board, &[0, 4, 2, 1, 7, 6, 3]);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+
+        // 5 blocks the last line anyone could still complete.
+        board.make_move(5).unwrap();
+        assert_eq!(board.get_board_state(), State::DRAW);
+        assert_eq!(board.get_empty_count(), 1);
+        assert_eq!(board.make_move(8), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn id_and_metadata_survive_play_and_reset() {
+        let mut board = Board::new_with_id(Player::X, Difficulty::EASY, "table-3");
+        assert_eq!(board.get_id(), "table-3");
+        assert_eq!(Board::new(Player::X, Difficulty::EASY).get_id(), "");
+
+        board.set_metadata("x", "Ada");
+        board.set_metadata("o", "Grace");
+        board.set_metadata("o", "Linus");
+        assert_eq!(board.get_metadata("missing"), None);
+
+        play(&mut board, &[4, 0]);
+        board.reset(Player::O);
+        assert_eq!(board.get_id(), "table-3");
+        assert_eq!(board.get_metadata("x"), Some("Ada".to_string()));
+        assert_eq!(board.get_metadata("o"), Some("Linus".to_string()));
+    }
+
+    #[test]
+    fn repeated_best_move_comes_from_the_cache() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[4, 0]);
+        let optimal = board.clone().optimal_moves();
+
+        assert!(optimal.contains(&board.get_best_move().unwrap()));
+        assert!(board.last_search_nodes() > 0);
+        for _ in 0..10 {
+            assert!(optimal.contains(&board.get_best_move().unwrap()));
+            assert_eq!(board.last_search_nodes(), 0);
+        }
+
+        // A move changes the position, so the next answer is searched again.
+        board.make_move(8).unwrap();
+        board.get_best_move().unwrap();
+        assert!(board.last_search_nodes() > 0);
+    }
+
+    #[test]
+    fn hint_primes_the_cache_for_the_move() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 4, 8]);
+        let hint = board.get_hint().unwrap();
+        assert!(board.clone().optimal_moves().contains(&hint));
+
+        board.get_best_move().unwrap();
+        assert_eq!(board.last_search_nodes(), 0);
+    }
+
+    #[test]
+    fn every_opening_is_optimal() {
+        // Perfect play draws from every first move, so no cell scores below
+        // the others.
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        assert_eq!(board.optimal_moves(), (0..9).collect::<Vec<usize>>());
+        assert!(board.matrix.iter().all(|&cell| cell == Player::EMPTY));
+        assert!(board.get_move_history().is_empty());
+
+        // After an edge opening O only holds the draw with the center, the
+        // corners beside the edge or the opposite edge.
+        board.make_move(1).unwrap();
+        assert_eq!(board.optimal_moves(), vec![0, 2, 4, 7]);
+        assert_eq!(board.get_move_history(), vec![1]);
+    }
+
+    #[test]
+    fn rematch_swaps_the_starter_unless_drawn() {
+        let mut board = Board::new(Player::X, Difficulty::MEDIUM);
+        play(&mut board, &[0, 3, 1, 4, 2]);
+        assert_eq!(board.get_winner(), Player::X);
+
+        board.rematch();
+        assert_eq!(board.get_start_player(), Player::O);
+        assert_eq!(board.get_current_turn(), Player::O);
+        assert_eq!(board.get_board_state(), State::INPROGRESS);
+        assert_eq!(board.get_winner(), Player::EMPTY);
+        assert!(board.get_move_history().is_empty());
+        assert_eq!(board.get_available_moves().len(), 9);
+        assert_eq!(board.get_difficulty(), Difficulty::MEDIUM);
+
+        play(&mut board, &DRAWN_GAME);
+        assert_eq!(board.get_board_state(), State::DRAW);
+        board.rematch();
+        assert_eq!(board.get_current_turn(), Player::O);
+    }
+
+    #[test]
+    fn threat_map_marks_wins_blocks_and_forks() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4]);
+        // X wins on 2 and has to block O on 5.
+        assert_eq!(board.threat_map(), vec![0, 0, 2, 0, 0, 1, 0, 0, 0]);
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 4, 8, 1]);
+        // O threatens 7, while 6 would give X two lines at once.
+        assert_eq!(board.threat_map(), vec![0, 0, 0, 0, 0, 0, 3, 1, 0]);
+
+        let mut finished = Board::new(Player::X, Difficulty::EASY);
+        play(&mut finished, &[0, 3, 1, 4, 2]);
+        assert_eq!(finished.threat_map(), vec![0; 9]);
+    }
+
+    #[test]
+    fn custom_search_scores_still_block() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 4, 1]);
+        let defaults = board.clone().evaluate_moves();
+
+        board.set_search_scores(10, 0, -1000).unwrap();
+        assert_eq!(board.optimal_moves(), vec![2]);
+        assert_eq!(board.get_best_move(), Some(2));
+
+        // Spelling out the defaults changes nothing.
+        board.set_search_scores(10, 0, -10).unwrap();
+        assert_eq!(board.evaluate_moves(), defaults);
+        assert_eq!(board.optimal_moves(), vec![2]);
+    }
+
+    #[test]
+    fn small_search_scores_still_prefer_a_slow_win_to_a_draw() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 1, 4, 8]);
+        board.set_search_scores(1, 0, -1).unwrap();
+        let scores = board.evaluate_moves();
+        assert!(scores.contains(&(3, 1)) && scores.contains(&(6, 1)), "{:?}", scores);
+        assert!(scores.contains(&(7, 0)), "{:?}", scores);
+        let best = board.get_best_move().unwrap();
+        assert!(scores.contains(&(best, 1)), "{} in {:?}", best, scores);
+    }
+
+    #[test]
+    fn extreme_search_scores_do_not_overflow() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        board.set_search_scores(i32::MAX, 0, i32::MIN).unwrap();
+        play(&mut board, &[0, 1, 4]);
+        assert_eq!(board.evaluate_moves().len(), 6);
+        assert_eq!(board.optimal_moves(), vec![8]);
+
+        let unordered = [(0, 0, -1), (1, 0, 0), (-1, 0, 1)];
+        for (win, draw, loss) in unordered {
+            assert!(!SearchScores { win, draw, loss }.is_ordered());
+        }
+    }
+
+    #[test]
+    fn random_position_is_legal_and_reproducible() {
+        for seed in 0..20 {
+            let board = Board::random_position(4, Difficulty::EASY, seed).unwrap();
+            assert_eq!(board.get_move_history().len(), 4);
+            assert_eq!(board.get_current_turn(), Player::X);
+            assert_eq!(board.matrix.iter().filter(|&&cell| cell == Player::X).count(), 2);
+            assert_eq!(board.matrix.iter().filter(|&&cell| cell == Player::O).count(), 2);
+            assert_eq!(board.get_board_state(), State::INPROGRESS);
+            assert!(board.validate().is_ok());
+            assert_eq!(board, Board::random_position(4, Difficulty::EASY, seed).unwrap());
+
+            // Asking for more moves than the game lasts stops at its end.
+            let board = Board::random_position(9, Difficulty::EASY, seed).unwrap();
+            assert!(board.is_game_over());
+            assert_eq!(board.computed_status(), (board.status, board.winner));
+        }
+    }
+
+    #[test]
+    fn open_lines_drop_as_lines_get_blocked() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.open_lines(), vec![8, 8]);
+
+        // The center sits on four lines, a corner on three.
+        play(&mut board, &[4]);
+        assert_eq!(board.open_lines(), vec![8, 4]);
+        play(&mut board, &[0]);
+        assert_eq!(board.open_lines(), vec![5, 4]);
+
+        play(&mut board, &[2, 1, 7, 6, 3, 5]);
+        assert!(board.is_dead_draw());
+        assert_eq!(board.open_lines(), vec![0, 0]);
+
+        assert_eq!(Board::new_sized(Player::X, Difficulty::EASY, 4).open_lines(), vec![10, 10]);
+    }
+
+    #[test]
+    fn codes_match_the_player_discriminants() {
+        assert_eq!((Player::EMPTY as i32, Player::X as i32, Player::O as i32), (0, 1, 2));
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        assert_eq!(board.get_current_turn_code(), 1);
+        assert_eq!(board.get_winner_code(), 0);
+        assert_eq!(board.get_matrix_codes(), vec![0; 9]);
+
+        play(&mut board, &[0, 3, 1, 4]);
+        assert_eq!(board.get_current_turn_code(), 1);
+        assert_eq!(board.get_matrix_codes(), vec![1, 1, 0, 2, 2, 0, 0, 0, 0]);
+        let codes: Vec<i32> = board.get_matrix().into_iter().map(|cell| cell as i32).collect();
+        assert_eq!(board.get_matrix_codes(), codes);
+        board.make_move(8).unwrap();
+        assert_eq!(board.get_current_turn_code(), 2);
+
+        board.make_move(5).unwrap();
+        assert_eq!(board.get_winner(), Player::O);
+        assert_eq!(board.get_winner_code(), 2);
+    }
+
+    #[test]
+    fn forced_win_in_finds_mates_only() {
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 3, 1, 4]);
+        let before = board.clone();
+        assert!(!board.forced_win_in(0));
+        for n in 1..=5 {
+            assert!(board.forced_win_in(n));
+        }
+        assert_eq!(board, before);
+
+        // X forks on 6 and wins with its second move.
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        play(&mut board, &[0, 1, 4, 8]);
+        assert!(!board.forced_win_in(1));
+        assert!(board.forced_win_in(2));
+
+        let mut board = Board::new(Player::X, Difficulty::EASY);
+        for n in 0..=5 {
+            assert!(!board.forced_win_in(n));
+        }
+        assert!(board.get_move_history().is_empty());
+    }
+
+    #[test]
+    fn distance_to_result_counts_plies_under_perfect_play() {
+        // X forks on 6, O blocks one line and X completes the other.
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        play(&mut board, &[0, 1, 4, 8]);
+        let before = board.clone();
+        assert_eq!(board.distance_to_result(), Some(3));
+        assert_eq!(board, before);
+
+        // The loser's count is the same line seen one ply later, and neither
+        // a depth limit nor custom scores change it.
+        board.make_move(6).unwrap();
+        board.set_max_depth(Some(1));
+        board.set_search_scores(100, 5, -100).unwrap();
+        assert_eq!(board.distance_to_result(), Some(2));
+
+        board.make_move(2).unwrap();
+        assert_eq!(board.distance_to_result(), Some(1));
+        board.make_move(3).unwrap();
+        assert_eq!(board.distance_to_result(), None);
+    }
+
+    #[test]
+    fn drawn_distance_follows_the_principal_variation() {
+        let mut board = Board::new(Player::X, Difficulty::DIFFICULT);
+        assert_eq!(board.distance_to_result(), Some(9));
+
+        board.set_early_draw(true);
+        let distance = board.distance_to_result().unwrap();
+        assert!(distance < 9);
+        assert_eq!(distance, board.principal_variation().len());
+
+        play(&mut board, &DRAWN_GAME);
+        assert_eq!(board.distance_to_result(), None);
+    }
+
+    #[test]
+    fn status_error_reports_each_broken_invariant() {
+        let corrupted = |moves: &[usize], status: State, winner: Player| {
+            let mut board = Board::new(Player::X, Difficulty::EASY);
+            play(&mut board, moves);
+            assert_eq!(board.status_error(), None);
+            board.status = status;
+            board.winner = winner;
+            board.status_error()
+        };
+        let x_wins: &[usize] = &[0, 3, 1, 4, 2];
+        let dead_draw: &[usize] = &[0, 4, 2, 1, 7, 6, 3, 5];
+
+        let cases = [
+            (x_wins, State::RESULTED, Player::EMPTY, "Resulted Board Has No Winner."),
+            (x_wins, State::RESULTED, Player::O, "Winner Does Not Match Completed Line."),
+            (&[], State::RESULTED, Player::X, "Winner Does Not Match Completed Line."),
+            (&DRAWN_GAME, State::DRAW, Player::X, "Drawn Board Has A Winner."),
+            (x_wins, State::DRAW, Player::EMPTY, "Drawn Board Has A Completed Line."),
+            (&[4], State::DRAW, Player::EMPTY, "Drawn Board Can Still Be Won."),
+            (&[], State::INPROGRESS, Player::X, "Board In Progress Has A Winner."),
+            (x_wins, State::INPROGRESS, Player::EMPTY, "Board In Progress Has A Completed Line."),
+            (&DRAWN_GAME, State::INPROGRESS, Player::EMPTY, "Board In Progress Is Already Drawn."),
+            // Dead positions only count as drawn with early draws on.
+            (dead_draw, State::DRAW, Player::EMPTY, "Status Does Not Match Board."),
+        ];
+        for (moves, status, winner, message) in cases {
+            assert_eq!(corrupted(moves, status, winner), Some(message), "after {:?}", moves);
+        }
+    }
 }